@@ -0,0 +1,86 @@
+//! Compares reading a large input whole into a buffer that grows as it fills
+//! with one pre-sized from the input's length, as `read_input` does with the
+//! size from file metadata.
+//!
+//! The wrappers behind `--read-timeout`, progress and `--decompress` only
+//! implement `read`, so the size hint `File` gives `read_to_end` is lost and
+//! the buffer has to grow by doubling. `Opaque` stands in for them here.
+//!
+//! Run with `cargo bench --bench presize`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Counts reallocations and the bytes they copy.
+struct Counting;
+
+static REALLOCS: AtomicUsize = AtomicUsize::new(0);
+static COPIED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        REALLOCS.fetch_add(1, Ordering::Relaxed);
+        COPIED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// A reader that hides the length of what it reads.
+struct Opaque<'a>(&'a [u8]);
+
+impl Read for Opaque<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+const SIZE: usize = 256 * 1024 * 1024;
+const RUNS: u32 = 10;
+
+/// Reads `input` whole `RUNS` times, returning the fastest time and the
+/// reallocations and copied bytes of one read.
+fn measure(input: &[u8], capacity: usize) -> (Duration, usize, usize) {
+    let mut fastest = Duration::MAX;
+    let mut reallocs = 0;
+    let mut copied = 0;
+    for _ in 0..RUNS {
+        REALLOCS.store(0, Ordering::Relaxed);
+        COPIED.store(0, Ordering::Relaxed);
+        let started = Instant::now();
+        let mut buffer = Vec::with_capacity(capacity);
+        Opaque(input).read_to_end(&mut buffer).unwrap();
+        black_box(&buffer);
+        fastest = fastest.min(started.elapsed());
+        reallocs = REALLOCS.load(Ordering::Relaxed);
+        copied = COPIED.load(Ordering::Relaxed);
+    }
+    (fastest, reallocs, copied)
+}
+
+fn main() {
+    let input: Vec<u8> = (0..SIZE).map(|i| b"lorem ipsum\n"[i % 12]).collect();
+    for (name, capacity) in [("growing", 0), ("pre-sized", SIZE)] {
+        let (fastest, reallocs, copied) = measure(&input, capacity);
+        println!(
+            "{:<10} {:>8.2?} {:>3} reallocations, {:>4} MiB copied",
+            name,
+            fastest,
+            reallocs,
+            copied / 1024 / 1024
+        );
+    }
+}
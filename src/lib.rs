@@ -0,0 +1,9032 @@
+//! Counting for the `wc` command line tool.
+//!
+//! [`Counter`] counts lines, words, characters and bytes of any reader, as
+//! the tool does for each plain input:
+//!
+//! ```
+//! let counts = wc::Counter::count_reader("one two\nthree\n".as_bytes()).unwrap();
+//! assert_eq!((counts.lines, counts.words, counts.bytes), (2, 3, 14));
+//! ```
+//!
+//! Input that arrives in pieces can be fed to a counter as it comes, split
+//! anywhere, even inside a word or a UTF-8 sequence:
+//!
+//! ```
+//! let mut counter = wc::Counter::new();
+//! for chunk in [&b"one tw"[..], b"o\nthr\xC3", b"\xA9e\n"] {
+//!     counter.update(chunk).unwrap();
+//! }
+//! let counts = counter.finalize().unwrap();
+//! assert_eq!((counts.lines, counts.words, counts.chars), (2, 3, 14));
+//! ```
+//!
+//! The tool itself is [`Args::parse`] followed by [`run`].
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{File, Metadata};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use memmap2::Mmap;
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag, TagEnd};
+use quick_xml::events::Event as XmlEvent;
+use quick_xml::Reader;
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+const USAGE: &str = "
+Usage: wc [OPTION]... [FILE]...
+
+Print newline, word, and byte counts for each FILE, and a total line if
+more than one FILE is specified.  A word is a non-zero-length sequence of
+printable characters delimited by white space.
+
+With no FILE, or when FILE is -, read standard input. A FILE starting with
+http:// or https:// is fetched, if wc was built with the http feature.
+
+A value shown as --option=VALUE may also be given as the next argument, as
+in --format json; an optional value, shown as [=VALUE], needs the '='.
+
+The options below may be used to select which counts are printed, always in
+the following order: newline, word, character, byte, maximum line length.
+  -c, --bytes            print the byte counts
+  -m, --chars            print the character counts
+  -l, --lines            print the newline counts
+  -L, --max-line-length  print the length of the longest line, in characters
+                           with tabs expanded; see --display-width
+      --max-line-length=verbose
+                         also print the line number of the widest line, as
+                           FILE:LINE; -LL does the same
+      --tab-width=N      make tabs advance -L to the next multiple of N
+                           columns (default 8)
+      --display-width    count the columns each character takes in a
+                           terminal for -L, so that wide East Asian characters
+                           take two and combining marks none
+  -w, --words            print the word counts
+      --files0-from=F    read input from the files specified by
+                           NUL-terminated names in file F;
+                           If F is - then read names from standard input
+      --emoji            print the emoji counts, counting ZWJ sequences and
+                           modified emoji as one
+      --whitespace       print the whitespace character counts
+      --distinct-bytes   print how many distinct byte values occur
+      --words-no-numbers
+                         print the word counts leaving out purely numeric words
+      --bytes-no-eol     print the byte counts leaving out line ending bytes
+      --blank-lines      print the counts of lines that are empty or hold only
+                           white space
+      --invalid-utf8     print the counts of malformed UTF-8 sequences, each of
+                           which is otherwise counted as one U+FFFD character
+      --tokens[=ENCODING]
+                         print the counts of LLM tokens in the BPE ENCODING:
+                           o200k_base (default), cl100k_base, or a tiktoken
+                           vocabulary file of base64 tokens and their ranks,
+                           if wc was built with the tokens feature
+      --reading-time[=WPM]
+                         print the estimated reading times in seconds, at
+                           WPM words per minute (default 230)
+      --unique-words     print the counts of distinct words, and of the words
+                           distinct across all FILEs in the total; every
+                           distinct word is kept in memory
+      --unique-lines     print the counts of distinct lines, and of the lines
+                           distinct across all FILEs in the total; every
+                           distinct line is kept in memory unless
+                           --approximate is given
+      --approximate      estimate --unique-lines in 16 KiB per input with a
+                           HyperLogLog sketch, typically within 1%
+      --unicode-words    find words at Unicode (UAX #29) word boundaries
+                           rather than between white space, so punctuation
+                           isn't a word and each CJK ideograph is one
+  -g, --graphemes        print the counts of user-perceived characters
+                           (extended grapheme clusters), so that an accented
+                           letter or an emoji ZWJ sequence counts as one
+  -e, --regexp=REGEX     print the counts of matches of the regular expression
+                           REGEX, after the other counts; may be repeated,
+                           each REGEX getting a column of its own
+      --paragraphs       print the paragraph counts, where paragraphs are
+                           separated by one or more blank lines
+      --sentences        print the sentence counts, splitting at Unicode
+                           sentence boundaries but not after common
+                           abbreviations such as 'Dr.'
+      --sentences-advanced
+                         print the sentence counts, not splitting after
+                           common abbreviations such as 'Dr.' or before a
+                           lowercase word; this is still a heuristic
+      --abbrev-file=FILE
+                         add the abbreviations listed one per line in FILE to
+                           those known to --sentences and
+                           --sentences-advanced
+      --numeric-total    print the total line without the 'total' label
+      --total=WHEN       when to print a line with total counts;
+                           WHEN can be: auto, always, only, never
+      --sort=KEY         print the files in order of KEY: name, from A to Z,
+                           or the long name of a count such as lines, words,
+                           bytes or chars, biggest first; the total stays last
+      --reverse          reverse the --sort order
+      --format=FORMAT    write the counts as text (default); as json, an
+                           array of objects keyed by the long names of the
+                           counts, with a type of total marking the total
+                           and a file that couldn't be counted given by its
+                           filename and error; or as csv or tsv records
+                           starting with the file name. Report lines are
+                           left out of all but text
+      --header           start csv and tsv output with a row of column names
+      --sort-json-keys   write the keys of each json object in alphabetical
+                           order; with --sort=name the files are in a fixed
+                           order too, so the output of two runs can be
+                           compared byte for byte
+      --quoting-style=WORD
+                         quote file names in text output in style WORD:
+                           literal, shell, shell-always, shell-escape,
+                           shell-escape-always, c or escape. By default only
+                           names holding control characters are quoted, as
+                           with shell-escape
+  -0, --print0           end each row of counts, csv or tsv with a NUL
+                           rather than a newline, so that names holding
+                           newlines can be read back with 'xargs -0'
+      --printf=FORMAT    write each row as FORMAT, with no newline added, where
+                           %l, %w, %m, %c, %L and %g are the counts of those
+                           options, %{NAME} is a count by its long name, %f
+                           is the file name and %% is %; \\n, \\t and \\\\ are
+                           escapes. Counts in FORMAT need not be selected
+      --percent          also print each file's counts as a percentage of the
+                           total
+      --decimal-sep=SEP  write SEP between the whole and fractional parts of
+                           percentages, means and other decimals; the default
+                           is the LC_NUMERIC locale's, such as ',' for de_DE
+      --merge            count the FILEs as one concatenated input, exactly as
+                           'cat FILE... | wc' would, and print only the total
+      --only=GLOB        only print the rows of files whose name matches GLOB;
+                           may be repeated
+      --only-in-total    make the total cover just the rows kept by --only
+  -z, --zero-terminated  end lines with NUL rather than newline, for the line
+                           counts and -L
+      --empty-as-one-line
+                         report an empty input as one line rather than zero
+      --read-timeout=SECS
+                         fail if no input arrives within SECS seconds
+      --no-glob          take FILEs containing *, ? or [ literally; otherwise
+                           they are expanded here when no file has that exact
+                           name, as on shells that leave them to the program.
+                           ** matches any number of directories
+  -r, --recursive        count every regular file under the directory FILEs,
+                           or under the current directory if there are none
+      --follow-symlinks  with -r, also follow symbolic links found inside
+                           directories; links given as FILEs are always
+                           followed
+      --respect-gitignore
+                         with -r, skip .git directories and whatever the
+                           .gitignore files in the directories walked ignore
+      --include=GLOB     with -r, only count the files matching GLOB; may be
+                           repeated
+      --exclude=GLOB     with -r, skip the files and directories matching
+                           GLOB; may be repeated. Like --only, a GLOB with a
+                           / is matched against the path below the directory
+                           FILE, and otherwise against the name alone
+      --decompress       count the decompressed contents of gzip, zstd, xz and
+                           bzip2 inputs, recognised by their first bytes
+      --archive          count each file inside zip and tar archives, which
+                           may be gzip-compressed, and print it as
+                           ARCHIVE:NAME before a subtotal line for ARCHIVE
+      --mmap             map regular files into memory rather than reading them
+      --no-mmap          never map files; by default only regular files of
+                           16 MiB or more are mapped
+      --jobs=N           count up to N files at once (default: the number of
+                           CPUs); the output keeps the order of the FILEs,
+                           and a single large FILE is split between N threads
+      --tui              show the counts in a table on the terminal as the
+                           FILEs are counted, sortable by any column, then
+                           print them as usual; if wc was built with the tui
+                           feature
+      --brackets         count (), [] and {} and report the first unmatched one
+      --skip-strings     ignore brackets inside quoted strings with --brackets
+      --markdown         count words and characters of Markdown prose only
+      --exclude-code     leave code blocks out of the --markdown counts
+      --html             count words and characters of visible HTML text only
+      --strip=MARKUP     count words and characters of prose only, removing
+                           markdown syntax and code blocks, html, or latex
+                           commands, comments and math
+      --code-blocks      count fenced and indented Markdown code blocks and
+                           the lines inside them
+      --count-continuation-lines
+                         report physical lines and logical lines, joining
+                           lines that end in a backslash to the next one
+      --columns=START-END[,START-END]...
+                         count characters and words within the given
+                           (1-based, inclusive) character columns of each line
+      --strip-comments=LANG
+                         remove rust, c, python or shell comments before
+                           counting; lines holding only a comment are dropped.
+                           Quoting is handled simply: raw strings, heredocs
+                           and docstrings are not recognised
+      --between=START:END
+                         only count the lines between a line matching the
+                           START regex and the next line matching END
+      --todo             count TODO, FIXME, XXX and HACK markers
+      --fail-on-tabs     exit with status 1 if any line contains a tab, naming
+                           the first such line of each file on standard error
+      --leading-only     only consider tabs in leading whitespace with
+                           --fail-on-tabs
+      --warn-no-newline  warn on standard error about each file whose last line
+                           doesn't end in a newline, which GNU wc wouldn't
+                           count as a line
+      --fail-no-newline  like --warn-no-newline, also exiting with status 1
+      --line-endings     count the lines ending in LF, CRLF and a bare CR,
+                           marking files that mix them
+      --fail-on-mixed-endings
+                         exit with status 1 if a file mixes LF, CRLF and CR
+                           line endings, naming it on standard error
+      --check-bom        report whether each file starts with a byte order mark
+      --require-bom      exit with status 1 if a file has no byte order mark
+      --forbid-bom       exit with status 1 if a file has a byte order mark
+      --detect-encoding  report each input's encoding, guessed from its byte
+                           order mark, on standard error
+      --no-detect        count input as UTF-8 even if it starts with a byte
+                           order mark; otherwise a UTF-8 BOM is left out of
+                           the characters and words, and UTF-16 and UTF-32
+                           input is decoded by its BOM
+      --auto-decode      decode input by its byte order mark, the default;
+                           undoes an earlier --no-detect
+      --encoding=NAME    decode input from the encoding NAME, such as
+                           windows-1252, latin1, utf-16le or shift_jis, before
+                           counting everything but bytes
+      --output=FILE      write the report to FILE rather than standard output,
+                           replacing what FILE held
+      --append           add the report to the end of the --output FILE,
+                           starting it on a line of its own
+      --output-encoding=ENC
+                         write the report, to standard output or the output
+                           file, in ENC: utf8 (default), utf8-bom, utf16le,
+                           utf16be, utf32le or utf32be
+  -f, --follow           keep counting the FILEs as they grow, like tail -f,
+                           writing the counts again whenever they change (in
+                           place on a terminal) until interrupted; a FILE
+                           that shrinks is counted again from the start
+      --sleep-interval=SECS
+                         seconds between checks for new data with --follow
+                           (default 1)
+      --interval=DURATION
+                         while counting a streamed input, such as a slow pipe,
+                           write its running counts to standard error every
+                           DURATION, e.g. 2s, 500ms or 1m
+      --no-progress      don't draw a progress bar with the throughput and
+                           time left on standard error, as is done when it's
+                           a terminal and the sizes of the FILEs are known
+      --progress-json    write JSON progress records to standard error
+      --progress-interval=SECS
+                         seconds between progress records (default 1)
+      --exit-if=METRIC OP N
+                         exit with status 1 if the total METRIC (the long
+                           name of a count, such as lines) compares to N with
+                           OP, one of <, <=, >, >=, == or !=; e.g. 'lines>100'
+      --urls             count http and https URLs
+      --xml              count XML elements, distinct element names and
+                           attributes, warning about malformed input
+      --json-stats       count the objects, arrays, strings, numbers, booleans
+                           and nulls in JSON input; invalid JSON is reported
+                           with its byte offset and gives exit status 1
+      --utf8-stats       count characters by UTF-8 encoded length (1-4 bytes)
+      --histogram=KIND   print a histogram of the lengths in characters of the
+                           lines or words of each file; KIND is line-length
+                           or word-length
+      --buckets=N[,N]... the largest length in each histogram bucket, with one
+                           more bucket for anything longer (default 40,80,120)
+      --ascii            count the bytes and characters of each file that
+                           aren't ASCII
+      --fail-non-ascii   exit with status 1 if a file isn't all ASCII, naming
+                           its first line that isn't on standard error
+      --classify         count the characters of each file that are letters,
+                           digits, punctuation, white space, control
+                           characters, or none of these (symbols, marks and
+                           emoji)
+      --stats            summarise the lengths in characters of the lines and
+                           words of each file: minimum, maximum, mean and
+                           median
+      --readability      print the Flesch-Kincaid grade level and reading ease
+                           of each file, estimating the syllables of English
+                           words
+      --char-range=START-END[,START-END]...
+                         count the characters in each range of hexadecimal
+                           code points, e.g. 4E00-9FFF or U+0400-U+04FF
+      --first-chars      count words by their (lowercased) first character
+      --repeated-lines   count the distinct lines that occur more than once
+                           and show the most repeated one; every distinct
+                           line is kept in memory
+      --cap-repeat-memory=N
+                         track at most N distinct lines for --repeated-lines
+      --frequency        list the most frequent words with their counts, most
+                           frequent first, as 'sort | uniq -c | sort -rn'
+                           would; every distinct word is kept in memory
+      --top=N            list the N most frequent words (default 10)
+      --ignore-case      fold words to lowercase for --frequency and
+                           --unique-words
+      --list             also list each --todo match with its line number and
+                           each distinct --urls URL and --xml element name
+      --help             display this help and exit
+
+Sending wc SIGUSR1, or SIGINFO (Ctrl-T) where there is one, writes the running
+counts of the input being streamed to standard error, as dd does.
+";
+
+/// The options and files of one `wc` invocation.
+#[derive(Debug)]
+pub struct Args {
+    files: Vec<String>,
+    /// Whether any FILE was named, directly or with `--files0-from`; without
+    /// one standard input is counted, but a `--recursive` walk that finds no
+    /// files leaves nothing to count
+    operands: bool,
+    /// A directory couldn't be walked for `--recursive`, or `--files0-from`
+    /// listed a name that can't be counted
+    names_failed: bool,
+    bytes: bool,
+    chars: bool,
+    lines: bool,
+    words: bool,
+    max_line_length: bool,
+    /// Whether the widest line is reported too, for `-LL`
+    longest_line: bool,
+    widths: Widths,
+    emoji: bool,
+    whitespace: bool,
+    sentences: bool,
+    advanced_sentences: bool,
+    distinct_bytes: bool,
+    words_no_numbers: bool,
+    bytes_no_eol: bool,
+    blank_lines: bool,
+    invalid_utf8: bool,
+    tokens: bool,
+    reading_time: bool,
+    /// Words per minute for `--reading-time`
+    wpm: u64,
+    /// The encoding given by `--tokens`, or the default once tokens are
+    /// counted
+    tokenizer: Option<Tokenizer>,
+    graphemes: bool,
+    unicode_words: bool,
+    paragraphs: bool,
+    unique_words: bool,
+    unique_lines: bool,
+    approximate: bool,
+    abbreviations: Vec<String>,
+    numeric_total: bool,
+    total: Total,
+    sort: Option<SortKey>,
+    reverse: bool,
+    format: Format,
+    header: bool,
+    sort_json_keys: bool,
+    print0: bool,
+    quoting: Quoting,
+    printf: Option<Vec<Piece>>,
+    percent: bool,
+    /// What separates the fraction of a decimal, for `--decimal-sep`
+    decimal_sep: char,
+    merge: bool,
+    only: Vec<String>,
+    only_in_total: bool,
+    empty_as_one_line: bool,
+    zero_terminated: bool,
+    read_timeout: Option<Duration>,
+    /// `--mmap` or `--no-mmap`, if either was given
+    mmap: Option<bool>,
+    decompress: bool,
+    archive: bool,
+    jobs: usize,
+    brackets: bool,
+    skip_strings: bool,
+    markdown: bool,
+    exclude_code: bool,
+    html: bool,
+    latex: bool,
+    xml: bool,
+    json_stats: bool,
+    code_blocks: bool,
+    continuation_lines: bool,
+    columns: Vec<(usize, usize)>,
+    between: Option<(Regex, Regex)>,
+    /// The patterns given by `-e`, each counted in a column of its own
+    regexps: Vec<Regex>,
+    strip_comments: Option<&'static CommentSyntax>,
+    todo: bool,
+    urls: bool,
+    utf8_stats: bool,
+    ascii: bool,
+    fail_non_ascii: bool,
+    classify: bool,
+    stats: bool,
+    readability: bool,
+    histogram: Option<Histogram>,
+    /// The largest length in each `--histogram` bucket but the last
+    buckets: Vec<u64>,
+    char_ranges: Vec<(char, char)>,
+    first_chars: bool,
+    repeated_lines: bool,
+    cap_repeat_memory: Option<usize>,
+    frequency: bool,
+    /// How many words `--frequency` lists
+    top: usize,
+    ignore_case: bool,
+    list: bool,
+    fail_on_tabs: bool,
+    leading_only: bool,
+    line_endings: bool,
+    fail_on_mixed_endings: bool,
+    warn_no_newline: bool,
+    fail_no_newline: bool,
+    check_bom: bool,
+    require_bom: bool,
+    forbid_bom: bool,
+    detect_encoding: bool,
+    /// Whether a byte order mark picks the decoder, cleared by `--no-detect`
+    detect_bom: bool,
+    /// The encoding given by `--encoding`
+    input_encoding: Option<&'static encoding_rs::Encoding>,
+    output_encoding: Encoding,
+    /// Where the report goes instead of stdout, for `--output`
+    output: Option<String>,
+    /// Whether the report is added to the end of `output`
+    append: bool,
+    progress_json: bool,
+    progress_bar: bool,
+    progress_interval: Duration,
+    follow: bool,
+    sleep_interval: Duration,
+    tui: bool,
+    /// How often to write running counts, for `--interval`
+    interval: Option<Duration>,
+    exit_if: Option<Condition>,
+}
+
+/// The long options that need a value, which can follow as `--option=VALUE`
+/// or as the next argument. Those whose value is optional only take `=`.
+const VALUED_OPTIONS: [&str; 31] = [
+    "--abbrev-file",
+    "--between",
+    "--buckets",
+    "--cap-repeat-memory",
+    "--char-range",
+    "--columns",
+    "--decimal-sep",
+    "--encoding",
+    "--exclude",
+    "--exit-if",
+    "--files0-from",
+    "--format",
+    "--histogram",
+    "--include",
+    "--interval",
+    "--jobs",
+    "--only",
+    "--output",
+    "--output-encoding",
+    "--printf",
+    "--progress-interval",
+    "--quoting-style",
+    "--read-timeout",
+    "--regexp",
+    "--sleep-interval",
+    "--sort",
+    "--strip-comments",
+    "--strip",
+    "--tab-width",
+    "--top",
+    "--total",
+];
+
+impl Args {
+    /// Parses command line arguments, without the program name. Like the
+    /// tool, this prints the usage and exits for `--help`, and reports an
+    /// invalid option and exits with status 1.
+    pub fn parse(args: Vec<String>) -> Self {
+        // -e takes the next argument as its pattern, even one starting with -,
+        // and a long option that needs a value may be given it the same way
+        let mut args = args.into_iter();
+        let mut joined = Vec::new();
+        while let Some(arg) = args.next() {
+            if VALUED_OPTIONS.contains(&arg.as_str()) {
+                let Some(value) = args.next() else {
+                    eprintln!("wc: option '{}' requires an argument", arg);
+                    eprintln!("Try 'wc --help' for more information.");
+                    std::process::exit(1);
+                };
+                joined.push(format!("{}={}", arg, value));
+            } else if arg == "-e" {
+                let Some(pattern) = args.next() else {
+                    eprintln!("wc: option requires an argument -- 'e'");
+                    eprintln!("Try 'wc --help' for more information.");
+                    std::process::exit(1);
+                };
+                joined.push(format!("--regexp={}", pattern));
+            } else if let Some(pattern) = arg.strip_prefix("-e") {
+                joined.push(format!("--regexp={}", pattern));
+            } else {
+                joined.push(arg);
+            }
+        }
+        let (mut files, options): (Vec<_>, Vec<_>) = joined
+            .into_iter()
+            .partition(|arg| arg == "-" || !arg.starts_with('-'));
+
+        let mut files0_from = None;
+        let mut recursive = false;
+        let mut glob = true;
+        let mut follow_symlinks = false;
+        let mut respect_gitignore = false;
+        let mut include = Vec::new();
+        let mut exclude = Vec::new();
+        let mut bytes = false;
+        let mut chars = false;
+        let mut lines = false;
+        let mut words = false;
+        let mut max_line_length = false;
+        let mut longest_line = false;
+        let mut widths = Widths::default();
+        let mut emoji = false;
+        let mut whitespace = false;
+        let mut sentences = false;
+        let mut advanced_sentences = false;
+        let mut distinct_bytes = false;
+        let mut words_no_numbers = false;
+        let mut bytes_no_eol = false;
+        let mut blank_lines = false;
+        let mut invalid_utf8 = false;
+        let mut tokens = false;
+        let mut reading_time = false;
+        let mut wpm = 230;
+        let mut tokenizer = None;
+        let mut graphemes = false;
+        let mut unicode_words = false;
+        let mut paragraphs = false;
+        let mut unique_words = false;
+        let mut unique_lines = false;
+        let mut approximate = false;
+        let mut abbreviations: Vec<String> = ABBREVIATIONS.iter().map(|a| a.to_string()).collect();
+        let mut numeric_total = false;
+        let mut total = Total::Auto;
+        let mut sort = None;
+        let mut reverse = false;
+        let mut format = Format::Text;
+        let mut header = false;
+        let mut sort_json_keys = false;
+        let mut print0 = false;
+        let mut quoting = None;
+        let mut printf = None;
+        let mut percent = false;
+        let mut decimal_sep = None;
+        let mut merge = false;
+        let mut only = Vec::new();
+        let mut only_in_total = false;
+        let mut empty_as_one_line = false;
+        let mut zero_terminated = false;
+        let mut read_timeout = None;
+        let mut mmap = None;
+        let mut decompress = false;
+        let mut archive = false;
+        let mut jobs = thread::available_parallelism().map_or(1, |n| n.get());
+        let mut brackets = false;
+        let mut skip_strings = false;
+        let mut markdown = false;
+        let mut exclude_code = false;
+        let mut html = false;
+        let mut latex = false;
+        let mut xml = false;
+        let mut json_stats = false;
+        let mut code_blocks = false;
+        let mut continuation_lines = false;
+        let mut columns = Vec::new();
+        let mut between = None;
+        let mut regexps = Vec::new();
+        let mut strip_comments = None;
+        let mut todo = false;
+        let mut urls = false;
+        let mut utf8_stats = false;
+        let mut ascii = false;
+        let mut fail_non_ascii = false;
+        let mut classify = false;
+        let mut stats = false;
+        let mut readability = false;
+        let mut histogram = None;
+        let mut buckets = vec![40, 80, 120];
+        let mut char_ranges = Vec::new();
+        let mut first_chars = false;
+        let mut repeated_lines = false;
+        let mut cap_repeat_memory = None;
+        let mut frequency = false;
+        let mut top = 10;
+        let mut ignore_case = false;
+        let mut list = false;
+        let mut fail_on_tabs = false;
+        let mut leading_only = false;
+        let mut line_endings = false;
+        let mut fail_on_mixed_endings = false;
+        let mut warn_no_newline = false;
+        let mut fail_no_newline = false;
+        let mut check_bom = false;
+        let mut require_bom = false;
+        let mut forbid_bom = false;
+        let mut detect_encoding = false;
+        let mut detect_bom = true;
+        let mut input_encoding = None;
+        let mut output_encoding = Encoding::Utf8;
+        let mut output = None;
+        let mut append = false;
+        let mut progress_json = false;
+        let mut progress_bar = true;
+        let mut progress_interval = Duration::from_secs(1);
+        let mut follow = false;
+        let mut sleep_interval = Duration::from_secs(1);
+        let mut tui = false;
+        let mut interval = None;
+        let mut exit_if = None;
+
+        options.iter().for_each(|option| {
+            if option.starts_with("--") {
+                match option.as_str() {
+                    "--bytes" => bytes = true,
+                    "--chars" => chars = true,
+                    "--lines" => lines = true,
+                    "--words" => words = true,
+                    "--max-line-length" => max_line_length = true,
+                    "--display-width" => widths.display = true,
+                    "--max-line-length=verbose" => {
+                        max_line_length = true;
+                        longest_line = true;
+                    }
+                    "--emoji" => emoji = true,
+                    "--whitespace" => whitespace = true,
+                    "--sentences" => sentences = true,
+                    "--sentences-advanced" => {
+                        sentences = true;
+                        advanced_sentences = true;
+                    }
+                    "--distinct-bytes" => distinct_bytes = true,
+                    "--words-no-numbers" => words_no_numbers = true,
+                    "--bytes-no-eol" => bytes_no_eol = true,
+                    "--blank-lines" => blank_lines = true,
+                    "--invalid-utf8" => invalid_utf8 = true,
+                    "--tokens" => tokens = true,
+                    "--reading-time" => reading_time = true,
+                    _ if option.starts_with("--reading-time=") => {
+                        let value = &option["--reading-time=".len()..];
+                        match value.parse() {
+                            Ok(n) if n > 0 => {
+                                reading_time = true;
+                                wpm = n;
+                            }
+                            _ => {
+                                eprintln!("wc: invalid words per minute: '{}'", value);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    "--graphemes" => graphemes = true,
+                    "--unicode-words" => unicode_words = true,
+                    "--paragraphs" => paragraphs = true,
+                    "--unique-words" => unique_words = true,
+                    "--unique-lines" => unique_lines = true,
+                    "--approximate" => approximate = true,
+                    "--numeric-total" => numeric_total = true,
+                    "--percent" => percent = true,
+                    "--header" => header = true,
+                    "--sort-json-keys" => sort_json_keys = true,
+                    "--print0" => print0 = true,
+                    "--merge" => merge = true,
+                    "--only-in-total" => only_in_total = true,
+                    "--empty-as-one-line" => empty_as_one_line = true,
+                    "--zero-terminated" => zero_terminated = true,
+                    "--brackets" => brackets = true,
+                    "--skip-strings" => skip_strings = true,
+                    "--markdown" => markdown = true,
+                    "--exclude-code" => exclude_code = true,
+                    "--html" => html = true,
+                    "--xml" => xml = true,
+                    "--json-stats" => json_stats = true,
+                    "--code-blocks" => code_blocks = true,
+                    "--count-continuation-lines" => continuation_lines = true,
+                    "--todo" => todo = true,
+                    "--urls" => urls = true,
+                    "--utf8-stats" => utf8_stats = true,
+                    "--ascii" => ascii = true,
+                    "--fail-non-ascii" => fail_non_ascii = true,
+                    "--classify" => classify = true,
+                    "--stats" => stats = true,
+                    "--readability" => readability = true,
+                    "--first-chars" => first_chars = true,
+                    "--repeated-lines" => repeated_lines = true,
+                    "--frequency" => frequency = true,
+                    "--ignore-case" => ignore_case = true,
+                    "--list" => list = true,
+                    "--fail-on-tabs" => fail_on_tabs = true,
+                    "--leading-only" => leading_only = true,
+                    "--line-endings" => line_endings = true,
+                    "--fail-on-mixed-endings" => fail_on_mixed_endings = true,
+                    "--warn-no-newline" => warn_no_newline = true,
+                    "--fail-no-newline" => fail_no_newline = true,
+                    "--check-bom" => check_bom = true,
+                    "--require-bom" => require_bom = true,
+                    "--forbid-bom" => forbid_bom = true,
+                    "--detect-encoding" => detect_encoding = true,
+                    "--no-detect" => detect_bom = false,
+                    "--append" => append = true,
+                    // Decoding by the BOM is the default now
+                    "--auto-decode" => detect_bom = true,
+                    "--progress-json" => progress_json = true,
+                    "--no-progress" => progress_bar = false,
+                    "--follow" => follow = true,
+                    "--tui" => tui = true,
+                    "--no-glob" => glob = false,
+                    "--recursive" => recursive = true,
+                    "--follow-symlinks" => follow_symlinks = true,
+                    "--respect-gitignore" => respect_gitignore = true,
+                    "--mmap" => mmap = Some(true),
+                    "--no-mmap" => mmap = Some(false),
+                    "--decompress" => decompress = true,
+                    "--archive" => archive = true,
+                    "--help" => {
+                        println!("{}", USAGE);
+                        std::process::exit(0);
+                    }
+                    _ if option.starts_with("--read-timeout=") => {
+                        let secs = &option["--read-timeout=".len()..];
+                        match parse_seconds(secs) {
+                            Some(timeout) => read_timeout = Some(timeout),
+                            None => {
+                                eprintln!("wc: invalid read timeout: '{}'", secs);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--interval=") => {
+                        let duration = &option["--interval=".len()..];
+                        match parse_duration(duration) {
+                            Some(duration) => interval = Some(duration),
+                            None => {
+                                eprintln!("wc: invalid interval: '{}'", duration);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--sleep-interval=") => {
+                        let secs = &option["--sleep-interval=".len()..];
+                        match parse_seconds(secs) {
+                            Some(interval) => sleep_interval = interval,
+                            None => {
+                                eprintln!("wc: invalid sleep interval: '{}'", secs);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--progress-interval=") => {
+                        let secs = &option["--progress-interval=".len()..];
+                        match parse_seconds(secs) {
+                            Some(interval) => progress_interval = interval,
+                            None => {
+                                eprintln!("wc: invalid progress interval: '{}'", secs);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--char-range=") => {
+                        for range in option["--char-range=".len()..].split(',') {
+                            match parse_char_range(range) {
+                                Some(range) => char_ranges.push(range),
+                                None => {
+                                    eprintln!("wc: invalid character range: '{}'", range);
+                                    std::process::exit(1);
+                                }
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--jobs=") => {
+                        let n = &option["--jobs=".len()..];
+                        match n.parse::<usize>() {
+                            Ok(n) if n > 0 => jobs = n,
+                            _ => {
+                                eprintln!("wc: invalid number of jobs: '{}'", n);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--cap-repeat-memory=") => {
+                        let cap = &option["--cap-repeat-memory=".len()..];
+                        match cap.parse::<usize>() {
+                            Ok(cap) if cap > 0 => cap_repeat_memory = Some(cap),
+                            _ => {
+                                eprintln!("wc: invalid line cap: '{}'", cap);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--decimal-sep=") => {
+                        let sep = &option["--decimal-sep=".len()..];
+                        let mut chars = sep.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => decimal_sep = Some(c),
+                            _ => {
+                                eprintln!("wc: invalid decimal separator: '{}'", sep);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--histogram=") => {
+                        let kind = &option["--histogram=".len()..];
+                        match Histogram::parse(kind) {
+                            Some(kind) => histogram = Some(kind),
+                            None => {
+                                eprintln!("wc: unknown histogram: '{}'", kind);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--buckets=") => {
+                        let list = &option["--buckets=".len()..];
+                        let parsed: Option<Vec<u64>> =
+                            list.split(',').map(|n| n.parse().ok()).collect();
+                        match parsed {
+                            Some(bounds) if bounds.windows(2).all(|w| w[0] < w[1]) => {
+                                buckets = bounds
+                            }
+                            _ => {
+                                eprintln!("wc: invalid buckets: '{}'", list);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--tokens=") => {
+                        let encoding = &option["--tokens=".len()..];
+                        match Tokenizer::load(encoding) {
+                            Ok(loaded) => {
+                                tokens = true;
+                                tokenizer = Some(loaded);
+                            }
+                            Err(e) => {
+                                eprintln!("{}", e);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--tab-width=") => {
+                        let n = &option["--tab-width=".len()..];
+                        match n.parse::<u64>() {
+                            Ok(n) if n > 0 => widths.tab = n,
+                            _ => {
+                                eprintln!("wc: invalid tab width: '{}'", n);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--top=") => {
+                        let n = &option["--top=".len()..];
+                        match n.parse::<usize>() {
+                            Ok(n) if n > 0 => top = n,
+                            _ => {
+                                eprintln!("wc: invalid number of words: '{}'", n);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--abbrev-file=") => {
+                        let file = &option["--abbrev-file=".len()..];
+                        match std::fs::read_to_string(file) {
+                            Ok(list) => abbreviations.extend(
+                                list.lines()
+                                    .map(|a| a.trim().trim_end_matches('.').to_lowercase())
+                                    .filter(|a| !a.is_empty()),
+                            ),
+                            Err(_) => {
+                                eprintln!("wc: {}: No such file or directory", file);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--printf=") => {
+                        match parse_template(&option["--printf=".len()..]) {
+                            Ok(template) => printf = Some(template),
+                            Err(directive) => {
+                                eprintln!("wc: invalid --printf directive '{}'", directive);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--quoting-style=") => {
+                        let style = &option["--quoting-style=".len()..];
+                        match Quoting::parse(style) {
+                            Some(style) => quoting = Some(style),
+                            None => {
+                                eprintln!("wc: invalid quoting style: '{}'", style);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--format=") => {
+                        let name = &option["--format=".len()..];
+                        match Format::parse(name) {
+                            Some(name) => format = name,
+                            None => {
+                                eprintln!("wc: unsupported output format: '{}'", name);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--total=") => {
+                        let when = &option["--total=".len()..];
+                        match Total::parse(when) {
+                            Some(when) => total = when,
+                            None => {
+                                eprintln!("wc: invalid argument '{}' for '--total'", when);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--sort=") => {
+                        let key = &option["--sort=".len()..];
+                        match SortKey::parse(key) {
+                            Some(key) => sort = Some(key),
+                            None => {
+                                eprintln!("wc: invalid argument '{}' for '--sort'", key);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    "--reverse" => reverse = true,
+                    _ if option.starts_with("--files0-from=") => {
+                        files0_from = Some(option["--files0-from=".len()..].to_string());
+                    }
+                    _ if option.starts_with("--include=") => {
+                        include.push(option["--include=".len()..].to_string());
+                    }
+                    _ if option.starts_with("--exclude=") => {
+                        exclude.push(option["--exclude=".len()..].to_string());
+                    }
+                    _ if option.starts_with("--only=") => {
+                        only.push(option["--only=".len()..].to_string());
+                    }
+                    _ if option.starts_with("--encoding=") => {
+                        let name = &option["--encoding=".len()..];
+                        match encoding_rs::Encoding::for_label(name.as_bytes()) {
+                            Some(encoding) => input_encoding = Some(encoding),
+                            None => {
+                                eprintln!("wc: unknown encoding: '{}'", name);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--output=") => {
+                        output = Some(option["--output=".len()..].to_string());
+                    }
+                    _ if option.starts_with("--output-encoding=") => {
+                        let name = &option["--output-encoding=".len()..];
+                        match Encoding::parse(name) {
+                            Some(encoding) => output_encoding = encoding,
+                            None => {
+                                eprintln!("wc: unsupported output encoding: '{}'", name);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--exit-if=") => {
+                        let condition = &option["--exit-if=".len()..];
+                        match Condition::parse(condition) {
+                            Some(condition) => exit_if = Some(condition),
+                            None => {
+                                eprintln!("wc: invalid condition: '{}'", condition);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--strip=") => match &option["--strip=".len()..] {
+                        "markdown" => {
+                            markdown = true;
+                            exclude_code = true;
+                        }
+                        "html" => html = true,
+                        "latex" => latex = true,
+                        markup => {
+                            eprintln!("wc: unsupported markup: '{}'", markup);
+                            std::process::exit(1);
+                        }
+                    },
+                    _ if option.starts_with("--strip-comments=") => {
+                        let lang = &option["--strip-comments=".len()..];
+                        match CommentSyntax::for_language(lang) {
+                            Some(syntax) => strip_comments = Some(syntax),
+                            None => {
+                                eprintln!("wc: unsupported comment language: '{}'", lang);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--between=") => {
+                        let patterns = &option["--between=".len()..];
+                        let parsed = patterns.split_once(':').and_then(|(start, end)| {
+                            Some((Regex::new(start).ok()?, Regex::new(end).ok()?))
+                        });
+                        match parsed {
+                            Some(patterns) => between = Some(patterns),
+                            None => {
+                                eprintln!("wc: invalid section patterns: '{}'", patterns);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--regexp=") => {
+                        let pattern = &option["--regexp=".len()..];
+                        match Regex::new(pattern) {
+                            Ok(regex) => regexps.push(regex),
+                            Err(_) => {
+                                eprintln!("wc: invalid regular expression: '{}'", pattern);
+                                std::process::exit(1);
+                            }
+                        }
+                    }
+                    _ if option.starts_with("--columns=") => {
+                        for range in option["--columns=".len()..].split(',') {
+                            match parse_column_range(range) {
+                                Some(range) => columns.push(range),
+                                None => {
+                                    eprintln!("wc: invalid column range: '{}'", range);
+                                    std::process::exit(1);
+                                }
+                            }
+                        }
+                    }
+                    _ => {
+                        eprintln!("wc: unrecognized option '{}'", option);
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                option
+                    .strip_prefix('-')
+                    .unwrap()
+                    .chars()
+                    .for_each(|opt| match opt {
+                        'c' => bytes = true,
+                        'm' => chars = true,
+                        'l' => lines = true,
+                        'w' => words = true,
+                        'L' => {
+                            longest_line |= max_line_length;
+                            max_line_length = true;
+                        }
+                        '0' => print0 = true,
+                        'r' => recursive = true,
+                        'z' => zero_terminated = true,
+                        'g' => graphemes = true,
+                        'f' => follow = true,
+                        x => {
+                            eprintln!("wc: invalid option -- '{}'", x);
+                            eprintln!("Try 'wc --help' for more information.");
+                            std::process::exit(1);
+                        }
+                    });
+            }
+        });
+
+        if glob {
+            files = files.into_iter().flat_map(expand_glob).collect();
+        }
+
+        if append && output.is_none() {
+            eprintln!("wc: --append needs --output");
+            std::process::exit(1);
+        }
+
+        let mut names_failed = false;
+        if let Some(list) = &files0_from {
+            if let Some(file) = files.first() {
+                eprintln!("wc: extra operand '{}'", file);
+                eprintln!("file operands cannot be combined with --files0-from");
+                std::process::exit(1);
+            }
+            (files, names_failed) = read_files0(list);
+        }
+
+        if recursive && files.is_empty() && files0_from.is_none() {
+            files.push(".".to_string());
+        }
+        let operands = !files.is_empty() || files0_from.is_some();
+        if recursive {
+            let mut walk = Walk {
+                follow_symlinks,
+                respect_gitignore,
+                include,
+                exclude,
+                ..Walk::default()
+            };
+            for file in files {
+                match std::fs::metadata(&file) {
+                    Ok(metadata) if metadata.is_dir() => {
+                        walk.root = PathBuf::from(&file);
+                        walk.dir(Path::new(&file), &mut Vec::new());
+                    }
+                    _ => walk.files.push(file),
+                }
+            }
+            names_failed |= walk.failed;
+            files = walk.files;
+        }
+
+        // Use default options (-c -l -w) if no counts were selected
+        let counts = [
+            bytes,
+            chars,
+            lines,
+            words,
+            max_line_length,
+            emoji,
+            whitespace,
+            sentences,
+            distinct_bytes,
+            words_no_numbers,
+            bytes_no_eol,
+            graphemes,
+            paragraphs,
+            unique_words,
+            unique_lines,
+            blank_lines,
+            invalid_utf8,
+            tokens,
+            reading_time,
+        ];
+        if !counts.contains(&true) && printf.is_none() {
+            bytes = true;
+            lines = true;
+            words = true;
+        }
+
+        let mut args = Args {
+            files,
+            operands,
+            names_failed,
+            bytes,
+            chars,
+            lines,
+            words,
+            max_line_length,
+            longest_line,
+            widths,
+            emoji,
+            whitespace,
+            sentences,
+            advanced_sentences,
+            distinct_bytes,
+            words_no_numbers,
+            bytes_no_eol,
+            blank_lines,
+            invalid_utf8,
+            tokens,
+            tokenizer,
+            reading_time,
+            wpm,
+            graphemes,
+            unicode_words,
+            paragraphs,
+            unique_words,
+            unique_lines,
+            approximate,
+            abbreviations,
+            numeric_total,
+            total,
+            sort,
+            reverse,
+            format,
+            header,
+            sort_json_keys,
+            print0,
+            // NUL-terminated rows are meant to be read back as they are
+            quoting: quoting.unwrap_or(if print0 {
+                Quoting::Literal
+            } else {
+                Quoting::Auto
+            }),
+            printf,
+            percent,
+            decimal_sep: decimal_sep.unwrap_or_else(locale_decimal_sep),
+            merge,
+            only,
+            only_in_total,
+            empty_as_one_line,
+            zero_terminated,
+            read_timeout,
+            mmap,
+            decompress,
+            archive,
+            jobs,
+            brackets,
+            skip_strings,
+            markdown,
+            exclude_code,
+            html,
+            latex,
+            xml,
+            json_stats,
+            code_blocks,
+            continuation_lines,
+            columns,
+            between,
+            regexps,
+            strip_comments,
+            todo,
+            urls,
+            utf8_stats,
+            ascii,
+            fail_non_ascii,
+            classify,
+            stats,
+            readability,
+            histogram,
+            buckets,
+            char_ranges,
+            first_chars,
+            repeated_lines,
+            cap_repeat_memory,
+            frequency,
+            top,
+            ignore_case,
+            list,
+            fail_on_tabs,
+            leading_only,
+            line_endings,
+            fail_on_mixed_endings,
+            warn_no_newline,
+            fail_no_newline,
+            check_bom,
+            require_bom,
+            forbid_bom,
+            detect_encoding,
+            detect_bom,
+            input_encoding,
+            output_encoding,
+            output,
+            append,
+            progress_json,
+            progress_bar,
+            progress_interval,
+            follow,
+            sleep_interval,
+            tui,
+            interval,
+            exit_if,
+        };
+        // A template's counts are shown through it
+        let template_counts = args
+            .printf
+            .iter()
+            .flatten()
+            .filter_map(|piece| match piece {
+                Piece::Count(metric) => Some(*metric),
+                _ => None,
+            });
+        for metric in template_counts.collect::<Vec<_>>() {
+            args.show(metric);
+        }
+        if args.counts(Metric::Tokens) && args.tokenizer.is_none() {
+            match Tokenizer::load("o200k_base") {
+                Ok(tokenizer) => args.tokenizer = Some(tokenizer),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        args
+    }
+
+    fn show(&mut self, metric: Metric) {
+        let shown = match metric {
+            Metric::Lines => &mut self.lines,
+            Metric::Words => &mut self.words,
+            Metric::Chars => &mut self.chars,
+            Metric::Bytes => &mut self.bytes,
+            Metric::MaxLineLength => &mut self.max_line_length,
+            Metric::Emoji => &mut self.emoji,
+            Metric::Whitespace => &mut self.whitespace,
+            Metric::Sentences => &mut self.sentences,
+            Metric::DistinctBytes => &mut self.distinct_bytes,
+            Metric::WordsNoNumbers => &mut self.words_no_numbers,
+            Metric::BytesNoEol => &mut self.bytes_no_eol,
+            Metric::Graphemes => &mut self.graphemes,
+            Metric::Paragraphs => &mut self.paragraphs,
+            Metric::UniqueWords => &mut self.unique_words,
+            Metric::UniqueLines => &mut self.unique_lines,
+            Metric::BlankLines => &mut self.blank_lines,
+            Metric::InvalidUtf8 => &mut self.invalid_utf8,
+            Metric::Tokens => &mut self.tokens,
+            Metric::ReadingTime => &mut self.reading_time,
+            // Every pattern is always shown
+            Metric::Matches(_) => return,
+        };
+        *shown = true;
+    }
+
+    /// The shown counts, in the order of their columns.
+    fn metrics(&self) -> impl Iterator<Item = Metric> + '_ {
+        Metric::ALL
+            .into_iter()
+            .filter(|metric| metric.shown(self))
+            .chain((0..self.regexps.len()).map(Metric::Matches))
+    }
+
+    /// Whether `metric` has to be counted, either to print it or to check it.
+    fn counts(&self, metric: Metric) -> bool {
+        metric.shown(self)
+            || self.exit_if.as_ref().is_some_and(|c| c.metric == metric)
+            || self.sort == Some(SortKey::Count(metric))
+            // Reading times are worked out from the words
+            || metric == Metric::Words && self.counts(Metric::ReadingTime)
+    }
+
+    /// The seconds it takes to read `words` at `--reading-time`'s pace,
+    /// rounded up.
+    fn reading_time(&self, words: u64) -> u64 {
+        if self.counts(Metric::ReadingTime) {
+            (words * 60).div_ceil(self.wpm)
+        } else {
+            0
+        }
+    }
+
+    /// Whether the file described by `metadata` should be mapped into memory.
+    fn maps(&self, metadata: &Metadata) -> bool {
+        let size = metadata.len();
+        let mmap = self.mmap.unwrap_or(size >= MMAP_THRESHOLD);
+        metadata.is_file() && size > 0 && mmap && !self.decompress
+    }
+
+    /// What ends each line of input.
+    fn delimiter(&self) -> char {
+        if self.zero_terminated {
+            '\0'
+        } else {
+            '\n'
+        }
+    }
+
+    /// The lines of `input`, without what ends them.
+    fn lines_of<'a>(&self, input: &'a str) -> Box<dyn Iterator<Item = &'a str> + 'a> {
+        if self.zero_terminated {
+            Box::new(input.split_terminator('\0'))
+        } else {
+            Box::new(input.lines())
+        }
+    }
+
+    /// The words of `input`, as `--unicode-words` finds them or otherwise
+    /// between white space.
+    fn words_of<'a>(&self, input: &'a str) -> Box<dyn Iterator<Item = &'a str> + 'a> {
+        if self.unicode_words {
+            Box::new(input.unicode_words())
+        } else {
+            Box::new(input.split_whitespace())
+        }
+    }
+
+    /// The sentences of `input`, split as `--sentences-advanced` asks.
+    fn sentences_in(&self, input: &str) -> u64 {
+        if self.advanced_sentences {
+            count_sentences(input, &self.abbreviations)
+        } else {
+            count_unicode_sentences(input, &self.abbreviations)
+        }
+    }
+
+    /// What ends each row of output.
+    fn terminator(&self) -> char {
+        if self.print0 {
+            '\0'
+        } else {
+            '\n'
+        }
+    }
+
+    /// Whether the byte count is all that has to be known about each input.
+    fn only_bytes(&self) -> bool {
+        let bom = self.check_bom || self.require_bom || self.forbid_bom;
+        let others_unused = Metric::ALL
+            .into_iter()
+            .all(|metric| metric == Metric::Bytes || !self.counts(metric));
+        let last_byte = self.warn_no_newline || self.fail_no_newline;
+        let reports = self.xml || self.json_stats;
+        self.streams() && !bom && others_unused && !self.decompress && !last_byte && !reports
+    }
+
+    /// Whether inputs can be streamed through a `Counter`: everything other
+    /// than the basic counts, `--xml` and `--json-stats` needs the whole input
+    /// at once.
+    fn streams(&self) -> bool {
+        let whole_input = [
+            self.counts(Metric::Emoji),
+            self.counts(Metric::Sentences),
+            self.counts(Metric::Graphemes),
+            self.counts(Metric::Paragraphs),
+            self.merge,
+            self.brackets,
+            self.markdown,
+            self.html,
+            self.latex,
+            self.code_blocks,
+            self.continuation_lines,
+            !self.columns.is_empty(),
+            self.between.is_some(),
+            !self.regexps.is_empty(),
+            self.strip_comments.is_some(),
+            self.todo,
+            self.urls,
+            self.utf8_stats,
+            self.ascii,
+            self.fail_non_ascii,
+            self.classify,
+            self.stats,
+            self.readability,
+            self.histogram.is_some(),
+            !self.char_ranges.is_empty(),
+            self.first_chars,
+            self.repeated_lines,
+            self.unicode_words,
+            self.fail_on_tabs,
+            self.line_endings,
+            self.fail_on_mixed_endings,
+            self.detect_encoding,
+            self.input_encoding.is_some(),
+            self.counts(Metric::Tokens),
+        ];
+        !whole_input.contains(&true)
+    }
+
+    /// Whether an input starting with `head` has to be decoded whole, being
+    /// UTF-16 or UTF-32 by its byte order mark.
+    fn decodes(&self, head: &[u8]) -> bool {
+        let (encoding, _) = Encoding::detect(head);
+        self.detect_bom && !matches!(encoding, Encoding::Utf8 | Encoding::Utf8Bom)
+    }
+}
+
+/// When to print the total row, set by `--total`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Total {
+    /// Only when more than one file was counted
+    Auto,
+    Always,
+    /// In place of the per-file rows, and without the 'total' label
+    Only,
+    Never,
+}
+
+impl Total {
+    fn parse(when: &str) -> Option<Self> {
+        match when {
+            "auto" => Some(Total::Auto),
+            "always" => Some(Total::Always),
+            "only" => Some(Total::Only),
+            "never" => Some(Total::Never),
+            _ => None,
+        }
+    }
+}
+
+/// What the files are ordered by, set by `--sort`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Name,
+    Count(Metric),
+}
+
+impl SortKey {
+    fn parse(key: &str) -> Option<Self> {
+        match key {
+            "name" => Some(SortKey::Name),
+            _ => Metric::parse(key).map(SortKey::Count),
+        }
+    }
+
+    /// Orders names from A to Z and counts biggest first, before `--reverse`.
+    fn compare(self, a: &WordCount, b: &WordCount) -> std::cmp::Ordering {
+        match self {
+            SortKey::Name => a.filename.cmp(&b.filename),
+            SortKey::Count(metric) => metric.value(b).cmp(&metric.value(a)),
+        }
+    }
+}
+
+/// A piece of a `--printf` template.
+#[derive(Debug)]
+enum Piece {
+    Text(String),
+    Count(Metric),
+    Filename,
+}
+
+/// Parses a `--printf` template: `%l`, `%w`, `%m`, `%c`, `%L` and `%g` stand for
+/// the counts of the matching options, `%{NAME}` for any count by its long
+/// name, `%f` for the file name and `%%` for `%`. `\n`, `\t` and `\\` are
+/// escapes, as the template is usually given in single quotes.
+fn parse_template(template: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        let piece = match c {
+            '%' => match chars.next() {
+                Some('%') => None,
+                Some('l') => Some(Piece::Count(Metric::Lines)),
+                Some('w') => Some(Piece::Count(Metric::Words)),
+                Some('m') => Some(Piece::Count(Metric::Chars)),
+                Some('c') => Some(Piece::Count(Metric::Bytes)),
+                Some('L') => Some(Piece::Count(Metric::MaxLineLength)),
+                Some('g') => Some(Piece::Count(Metric::Graphemes)),
+                Some('f') => Some(Piece::Filename),
+                Some('{') => {
+                    let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    let metric = Metric::parse(&name).ok_or(format!("%{{{}}}", name))?;
+                    Some(Piece::Count(metric))
+                }
+                Some(c) => return Err(format!("%{}", c)),
+                None => return Err("%".to_string()),
+            },
+            '\\' => {
+                text.push(match chars.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('0') => '\0',
+                    Some('\\') => '\\',
+                    Some(c) => return Err(format!("\\{}", c)),
+                    None => return Err("\\".to_string()),
+                });
+                continue;
+            }
+            c => {
+                text.push(c);
+                continue;
+            }
+        };
+        match piece {
+            Some(piece) => {
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(piece);
+            }
+            None => text.push('%'),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+/// How the counts are written, set by `--format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    /// An array of objects, one per row, with a count per shown column
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl Format {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            "tsv" => Some(Format::Tsv),
+            _ => None,
+        }
+    }
+
+    /// Escapes `field` for a CSV or TSV record.
+    fn field(self, field: &str) -> Cow<'_, str> {
+        match self {
+            Format::Csv if field.contains([',', '"', '\n', '\r']) => {
+                Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+            }
+            // TSV can't quote, so the characters it relies on are escaped
+            Format::Tsv if field.contains(['\\', '\t', '\n', '\r']) => Cow::Owned(
+                field
+                    .replace('\\', "\\\\")
+                    .replace('\t', "\\t")
+                    .replace('\n', "\\n")
+                    .replace('\r', "\\r"),
+            ),
+            _ => Cow::Borrowed(field),
+        }
+    }
+
+    fn separator(self) -> &'static str {
+        match self {
+            Format::Tsv => "\t",
+            _ => ",",
+        }
+    }
+}
+
+/// How file names are quoted in text output, set by `--quoting-style`; the
+/// styles are those of GNU `ls`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quoting {
+    /// `shell-escape`, but only for names holding control characters, as
+    /// GNU wc does
+    Auto,
+    Literal,
+    Shell,
+    ShellAlways,
+    ShellEscape,
+    ShellEscapeAlways,
+    C,
+    Escape,
+}
+
+impl Quoting {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "literal" => Some(Quoting::Literal),
+            "shell" => Some(Quoting::Shell),
+            "shell-always" => Some(Quoting::ShellAlways),
+            "shell-escape" => Some(Quoting::ShellEscape),
+            "shell-escape-always" => Some(Quoting::ShellEscapeAlways),
+            "c" => Some(Quoting::C),
+            "escape" => Some(Quoting::Escape),
+            _ => None,
+        }
+    }
+
+    fn quote(self, name: &str) -> Cow<'_, str> {
+        let needs_escape = name.contains(char::is_control);
+        let needs_quotes = needs_escape
+            || name.is_empty()
+            || name.starts_with(['~', '#'])
+            || !name
+                .chars()
+                .all(|c| c.is_alphanumeric() || "-_./:,+@%=^".contains(c));
+        match self {
+            Quoting::Auto if needs_escape => Quoting::ShellEscape.quote(name),
+            Quoting::Shell | Quoting::ShellEscape if !needs_quotes => Cow::Borrowed(name),
+            Quoting::Shell | Quoting::ShellAlways => {
+                Cow::Owned(format!("'{}'", name.replace('\'', "'\\''")))
+            }
+            Quoting::ShellEscape | Quoting::ShellEscapeAlways => {
+                // Control characters are written as $'...' strings between
+                // the quoted runs of everything else
+                let mut quoted = String::new();
+                let mut chars = name.chars().peekable();
+                while let Some(&c) = chars.peek() {
+                    let control = c.is_control();
+                    let mut run = String::new();
+                    while let Some(c) = chars.next_if(|c| c.is_control() == control) {
+                        if control {
+                            run.push_str(&c_escape(c));
+                        } else {
+                            run.push(c);
+                        }
+                    }
+                    if control {
+                        quoted.push_str(&format!("$'{}'", run));
+                    } else {
+                        quoted.push_str(&format!("'{}'", run.replace('\'', "'\\''")));
+                    }
+                }
+                if quoted.is_empty() {
+                    quoted.push_str("''");
+                }
+                Cow::Owned(quoted)
+            }
+            Quoting::C => {
+                let escaped: String = name.chars().map(c_escape).collect();
+                Cow::Owned(format!("\"{}\"", escaped.replace('"', "\\\"")))
+            }
+            Quoting::Escape => Cow::Owned(
+                name.chars()
+                    .map(|c| match c {
+                        ' ' => Cow::Borrowed("\\ "),
+                        c => c_escape(c),
+                    })
+                    .collect(),
+            ),
+            _ => Cow::Borrowed(name),
+        }
+    }
+}
+
+/// Writes `c` as it would appear in a C string, leaving printable
+/// characters as they are.
+fn c_escape(c: char) -> Cow<'static, str> {
+    match c {
+        '\\' => Cow::Borrowed("\\\\"),
+        '\x07' => Cow::Borrowed("\\a"),
+        '\x08' => Cow::Borrowed("\\b"),
+        '\t' => Cow::Borrowed("\\t"),
+        '\n' => Cow::Borrowed("\\n"),
+        '\x0b' => Cow::Borrowed("\\v"),
+        '\x0c' => Cow::Borrowed("\\f"),
+        '\r' => Cow::Borrowed("\\r"),
+        c if c.is_control() && (c as u32) < 0x100 => Cow::Owned(format!("\\{:03o}", c as u32)),
+        c if c.is_control() => Cow::Owned(format!("\\u{:04x}", c as u32)),
+        c => Cow::Owned(c.to_string()),
+    }
+}
+
+/// A count that can be printed as a column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Metric {
+    Lines,
+    Words,
+    Chars,
+    Bytes,
+    MaxLineLength,
+    Emoji,
+    Whitespace,
+    Sentences,
+    DistinctBytes,
+    WordsNoNumbers,
+    BytesNoEol,
+    Graphemes,
+    Paragraphs,
+    UniqueWords,
+    UniqueLines,
+    BlankLines,
+    InvalidUtf8,
+    Tokens,
+    ReadingTime,
+    /// The matches of the `-e` pattern at this index
+    Matches(usize),
+}
+
+impl Metric {
+    const ALL: [Metric; 19] = [
+        Metric::Lines,
+        Metric::Words,
+        Metric::Chars,
+        Metric::Bytes,
+        Metric::MaxLineLength,
+        Metric::Emoji,
+        Metric::Whitespace,
+        Metric::Sentences,
+        Metric::DistinctBytes,
+        Metric::WordsNoNumbers,
+        Metric::BytesNoEol,
+        Metric::Graphemes,
+        Metric::Paragraphs,
+        Metric::UniqueWords,
+        Metric::UniqueLines,
+        Metric::BlankLines,
+        Metric::InvalidUtf8,
+        Metric::Tokens,
+        Metric::ReadingTime,
+    ];
+
+    fn parse(name: &str) -> Option<Self> {
+        Metric::ALL.into_iter().find(|metric| metric.name() == name)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Metric::Lines => "lines",
+            Metric::Words => "words",
+            Metric::Chars => "chars",
+            Metric::Bytes => "bytes",
+            Metric::MaxLineLength => "max-line-length",
+            Metric::Emoji => "emoji",
+            Metric::Whitespace => "whitespace",
+            Metric::Sentences => "sentences",
+            Metric::DistinctBytes => "distinct-bytes",
+            Metric::WordsNoNumbers => "words-no-numbers",
+            Metric::BytesNoEol => "bytes-no-eol",
+            Metric::Graphemes => "graphemes",
+            Metric::Paragraphs => "paragraphs",
+            Metric::UniqueWords => "unique-words",
+            Metric::UniqueLines => "unique-lines",
+            Metric::BlankLines => "blank-lines",
+            Metric::InvalidUtf8 => "invalid-utf8",
+            Metric::Tokens => "tokens",
+            Metric::ReadingTime => "reading-time",
+            Metric::Matches(_) => "matches",
+        }
+    }
+
+    /// The name of the column, telling apart each pattern's matches.
+    fn label(self, args: &Args) -> Cow<'static, str> {
+        match self {
+            Metric::Matches(i) => Cow::Owned(format!("matches:{}", args.regexps[i].as_str())),
+            _ => Cow::Borrowed(self.name()),
+        }
+    }
+
+    fn shown(self, args: &Args) -> bool {
+        match self {
+            Metric::Lines => args.lines,
+            Metric::Words => args.words,
+            Metric::Chars => args.chars,
+            Metric::Bytes => args.bytes,
+            Metric::MaxLineLength => args.max_line_length,
+            Metric::Emoji => args.emoji,
+            Metric::Whitespace => args.whitespace,
+            Metric::Sentences => args.sentences,
+            Metric::DistinctBytes => args.distinct_bytes,
+            Metric::WordsNoNumbers => args.words_no_numbers,
+            Metric::BytesNoEol => args.bytes_no_eol,
+            Metric::Graphemes => args.graphemes,
+            Metric::Paragraphs => args.paragraphs,
+            Metric::UniqueWords => args.unique_words,
+            Metric::UniqueLines => args.unique_lines,
+            Metric::BlankLines => args.blank_lines,
+            Metric::InvalidUtf8 => args.invalid_utf8,
+            Metric::Tokens => args.tokens,
+            Metric::ReadingTime => args.reading_time,
+            Metric::Matches(i) => i < args.regexps.len(),
+        }
+    }
+
+    fn value(self, wc: &WordCount) -> u64 {
+        match self {
+            Metric::Lines => wc.lines,
+            Metric::Words => wc.words,
+            Metric::Chars => wc.chars,
+            Metric::Bytes => wc.bytes,
+            Metric::MaxLineLength => wc.max_line_length,
+            Metric::Emoji => wc.emoji,
+            Metric::Whitespace => wc.whitespace,
+            Metric::Sentences => wc.sentences,
+            Metric::DistinctBytes => wc.byte_set.iter().map(|w| w.count_ones() as u64).sum(),
+            Metric::WordsNoNumbers => wc.words_no_numbers,
+            Metric::BytesNoEol => wc.bytes_no_eol,
+            Metric::Graphemes => wc.graphemes,
+            Metric::Paragraphs => wc.paragraphs,
+            Metric::UniqueWords => wc.word_set.len() as u64,
+            Metric::UniqueLines => wc.line_set.len(),
+            Metric::BlankLines => wc.blank_lines,
+            Metric::InvalidUtf8 => wc.invalid_utf8,
+            Metric::Tokens => wc.tokens,
+            Metric::ReadingTime => wc.reading_time,
+            Metric::Matches(i) => wc.matches.get(i).copied().unwrap_or(0),
+        }
+    }
+}
+
+/// A threshold check such as `lines>10000`, used by `--exit-if`.
+#[derive(Debug)]
+struct Condition {
+    metric: Metric,
+    op: &'static str,
+    value: u64,
+}
+
+impl Condition {
+    fn parse(condition: &str) -> Option<Self> {
+        let start = condition.find(['<', '>', '=', '!'])?;
+        let (metric, rest) = condition.split_at(start);
+        let op = ["<=", ">=", "==", "!=", "<", ">"]
+            .into_iter()
+            .find(|op| rest.starts_with(op))?;
+        Some(Condition {
+            metric: Metric::parse(metric.trim())?,
+            op,
+            value: rest[op.len()..].trim().parse().ok()?,
+        })
+    }
+
+    fn holds(&self, wc: &WordCount) -> bool {
+        let actual = self.metric.value(wc);
+        match self.op {
+            "<" => actual < self.value,
+            "<=" => actual <= self.value,
+            ">" => actual > self.value,
+            ">=" => actual >= self.value,
+            "==" => actual == self.value,
+            _ => actual != self.value,
+        }
+    }
+}
+
+/// Expands a FILE operand that is a glob into the paths matching it, in name
+/// order. Operands naming an existing file, and globs matching nothing, are
+/// kept as they are.
+fn expand_glob(pattern: String) -> Vec<String> {
+    let is_glob = |component: &str| component.contains(['*', '?', '[']);
+    if !is_glob(&pattern) || std::fs::symlink_metadata(&pattern).is_ok() {
+        return vec![pattern];
+    }
+
+    let join = |path: &str, name: &str| match path {
+        "" => name.to_string(),
+        _ if path.ends_with(std::path::is_separator) => format!("{}{}", path, name),
+        _ => format!("{}/{}", path, name),
+    };
+    // Names in `dir` in order, leaving out hidden ones unless `pattern` asks
+    // for them
+    let names = |dir: &str, pattern: &str| {
+        let dir = if dir.is_empty() { "." } else { dir };
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| !name.starts_with('.') || pattern.starts_with('.'))
+            .collect();
+        names.sort();
+        names
+    };
+
+    let root_len = pattern.len() - pattern.trim_start_matches(std::path::is_separator).len();
+    let mut paths = vec![pattern[..root_len].to_string()];
+    for component in pattern[root_len..]
+        .split(std::path::is_separator)
+        .filter(|component| !component.is_empty())
+    {
+        let mut next = Vec::new();
+        for path in &paths {
+            if component == "**" {
+                // The directory itself and every directory below it, without
+                // following symbolic links that could lead back up the tree
+                let mut pending = vec![path.clone()];
+                while let Some(dir) = pending.pop() {
+                    let is_dir =
+                        |path: &String| std::fs::symlink_metadata(path).is_ok_and(|m| m.is_dir());
+                    let children = names(&dir, "").into_iter().map(|name| join(&dir, &name));
+                    let subdirs: Vec<String> = children.filter(is_dir).collect();
+                    pending.extend(subdirs.into_iter().rev());
+                    next.push(dir);
+                }
+            } else if is_glob(component) {
+                let matches = names(path, component);
+                let matches = matches.iter().filter(|name| glob_match(component, name));
+                next.extend(matches.map(|name| join(path, name)));
+            } else {
+                next.push(join(path, component));
+            }
+        }
+        paths = next;
+    }
+
+    paths.retain(|path| !path.is_empty() && std::fs::symlink_metadata(path).is_ok());
+    if paths.is_empty() {
+        paths.push(pattern);
+    }
+    paths
+}
+
+/// Matches `text` against a shell-style glob supporting `*`, `?` and `[...]`
+/// character classes (negated with `!` or `^`).
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where to resume after the most recent `*` fails to match
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => match_class(&pattern[p..], text[t]),
+            Some(&c) => (c == text[t]).then_some(1),
+            None => None,
+        };
+        match (step, backtrack) {
+            (Some(len), _) => {
+                p += len;
+                t += 1;
+            }
+            (None, Some((star, start))) => {
+                p = star + 1;
+                t = start + 1;
+                backtrack = Some((star, start + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Matches `c` against the `[...]` class at the start of `pattern`, returning
+/// the length of the class if it matches. An unterminated `[` is literal.
+fn match_class(pattern: &[char], c: char) -> Option<usize> {
+    let Some(end) = pattern
+        .iter()
+        .skip(2)
+        .position(|&p| p == ']')
+        .map(|i| i + 2)
+    else {
+        return (c == '[').then_some(1);
+    };
+    let (negated, class) = match pattern[1] {
+        '!' | '^' => (true, &pattern[2..end]),
+        _ => (false, &pattern[1..end]),
+    };
+
+    let mut matched = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            matched |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            matched |= class[i] == c;
+            i += 1;
+        }
+    }
+    (matched != negated).then_some(end + 1)
+}
+
+/// Whether a filename is kept by `--only`. Patterns without a `/` are matched
+/// against the last path component, so `*.rs` matches `src/main.rs`.
+fn only_matches(patterns: &[String], filename: &str) -> bool {
+    patterns.is_empty()
+        || patterns.iter().any(|pattern| {
+            let name = if pattern.contains('/') {
+                filename
+            } else {
+                filename.rsplit('/').next().unwrap_or(filename)
+            };
+            glob_match(pattern, name)
+        })
+}
+
+// Abbreviations (lowercase, without the final '.') that don't end a sentence
+const ABBREVIATIONS: [&str; 24] = [
+    "mr", "mrs", "ms", "dr", "prof", "sr", "jr", "st", "vs", "etc", "e.g", "i.e", "inc", "ltd",
+    "co", "corp", "fig", "no", "vol", "approx", "dept", "est", "cf", "al",
+];
+
+/// Counts sentences as runs of words ending in `.`, `!` or `?`. A full stop
+/// after a known abbreviation or a single-letter initial, or one followed by a
+/// lowercase word, isn't treated as the end of a sentence. Decimal numbers
+/// never end a sentence since the `.` isn't at the end of the word.
+fn count_sentences(input: &str, abbreviations: &[String]) -> u64 {
+    let mut sentences = 0;
+    let mut in_sentence = false;
+    let mut words = input.split_whitespace().peekable();
+
+    while let Some(word) = words.next() {
+        in_sentence = true;
+        // Closing quotes and brackets may follow the terminator
+        let word = word.trim_end_matches(['"', '\'', ')', ']', '\u{201D}', '\u{2019}']);
+        if !word.ends_with(['.', '!', '?']) {
+            continue;
+        }
+
+        if is_abbreviation(word, abbreviations) {
+            continue;
+        }
+        if words
+            .peek()
+            .and_then(|next| next.chars().find(|c| c.is_alphanumeric()))
+            .is_some_and(char::is_lowercase)
+        {
+            continue;
+        }
+
+        sentences += 1;
+        in_sentence = false;
+    }
+
+    // Trailing words without a terminator still make up a sentence
+    sentences + in_sentence as u64
+}
+
+/// Counts runs of lines that aren't blank, a blank line being empty or only
+/// white space.
+fn count_paragraphs(input: &str) -> u64 {
+    let mut paragraphs = 0;
+    let mut in_paragraph = false;
+    for line in input.lines() {
+        let blank = line.trim().is_empty();
+        paragraphs += (!blank && !in_paragraph) as u64;
+        in_paragraph = !blank;
+    }
+    paragraphs
+}
+
+/// Whether `word` ends in a full stop that belongs to a known abbreviation or
+/// a single-letter initial rather than ending a sentence.
+fn is_abbreviation(word: &str, abbreviations: &[String]) -> bool {
+    if !word.ends_with('.') || word.ends_with("..") {
+        return false;
+    }
+    let stem = word
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .trim_end_matches('.')
+        .to_lowercase();
+    let initial = stem.chars().count() == 1 && stem.chars().all(char::is_alphabetic);
+    initial || abbreviations.contains(&stem)
+}
+
+/// Counts sentences between Unicode (UAX #29) sentence boundaries, which
+/// cover terminators such as `。` and `‼` and don't break after an ellipsis
+/// or a full stop followed by a lowercase word. A boundary after a known
+/// abbreviation or an initial is ignored.
+fn count_unicode_sentences(input: &str, abbreviations: &[String]) -> u64 {
+    let mut sentences = 0;
+    let mut continued = false;
+    for sentence in input.unicode_sentences() {
+        sentences += !continued as u64;
+        continued = sentence
+            .split_whitespace()
+            .next_back()
+            .is_some_and(|word| is_abbreviation(word, abbreviations));
+    }
+    sentences
+}
+
+/// How many columns characters take for `-L`.
+#[derive(Debug, Clone, Copy)]
+struct Widths {
+    /// Tabs advance to the next multiple of this, set by `--tab-width`
+    tab: u64,
+    /// Whether characters are as wide as a terminal shows them rather than
+    /// one column each, for `--display-width`
+    display: bool,
+}
+
+impl Default for Widths {
+    fn default() -> Self {
+        Widths {
+            tab: 8,
+            display: false,
+        }
+    }
+}
+
+impl Widths {
+    /// The width of a line `width` columns wide once `c` is added.
+    fn advance(self, width: u64, c: char) -> u64 {
+        match c {
+            '\t' => width + self.tab - width % self.tab,
+            // Control characters take no columns
+            _ if self.display => width + c.width().unwrap_or(0) as u64,
+            _ => width + 1,
+        }
+    }
+}
+
+/// The display width of the widest line and the 1-based number of the first
+/// line that wide, with characters as wide as `widths` makes them and
+/// carriage returns and form feeds starting over, as in GNU wc. Widths also
+/// start over at `delimiter`, which is what ends a numbered line.
+fn widest_line(input: &str, delimiter: char, widths: Widths) -> (u64, u64) {
+    let mut max = 0;
+    let mut widest = 1;
+    let mut width = 0;
+    let mut line = 1;
+    for c in input.chars() {
+        let ends_width = matches!(c, '\n' | '\r' | '\x0c') || c == delimiter;
+        if ends_width {
+            if width > max {
+                max = width;
+                widest = line;
+            }
+            width = 0;
+            line += (c == delimiter) as u64;
+        } else {
+            width = widths.advance(width, c);
+        }
+    }
+    if width > max {
+        max = width;
+        widest = line;
+    }
+    (max, widest)
+}
+
+/// Whether a word is purely numeric, like `42`, `3.14` or `(1,000)`, once
+/// surrounding punctuation is trimmed.
+fn is_numeric_word(word: &str) -> bool {
+    let core = word.trim_matches(|c: char| c.is_ascii_punctuation());
+    !core.is_empty()
+        && core
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+}
+
+/// The 1-based number of the first line containing a tab, or with
+/// `leading_only` the first line whose indentation contains one.
+fn first_tab_line(input: &str, leading_only: bool) -> Option<usize> {
+    input
+        .lines()
+        .position(|line| {
+            let line = if leading_only {
+                &line[..line.len() - line.trim_start().len()]
+            } else {
+                line
+            };
+            line.contains('\t')
+        })
+        .map(|i| i + 1)
+}
+
+/// Reads the NUL-terminated file names listed in `list`, or on standard input
+/// if it is `-`, exiting on a read error or an empty name.
+fn read_files0(list: &str) -> (Vec<String>, bool) {
+    let names = if list == "-" {
+        let mut names = Vec::new();
+        io::stdin().read_to_end(&mut names).map(|_| names)
+    } else {
+        std::fs::read(list)
+    };
+    let names = match names {
+        Ok(names) => names,
+        Err(e) => {
+            eprintln!("wc: cannot open '{}' for reading: {}", list, describe(&e));
+            std::process::exit(1);
+        }
+    };
+
+    let mut files = Vec::new();
+    let mut failed = false;
+    // The last name may or may not be terminated
+    let names = names.strip_suffix(b"\0").unwrap_or(&names);
+    if names.is_empty() {
+        return (files, failed);
+    }
+    // A name that can't be counted is reported, and the rest are counted
+    for (i, name) in names.split(|&b| b == 0).enumerate() {
+        match std::str::from_utf8(name) {
+            Ok("") => eprintln!("wc: {}:{}: invalid zero-length file name", list, i + 1),
+            Ok("-") if list == "-" => eprintln!(
+                "wc: when reading file names from standard input, no file name of '-' allowed"
+            ),
+            Ok(name) => {
+                files.push(name.to_string());
+                continue;
+            }
+            Err(_) => eprintln!(
+                "wc: {}: file name is not valid UTF-8",
+                String::from_utf8_lossy(name)
+            ),
+        }
+        failed = true;
+    }
+    (files, failed)
+}
+
+/// A `--recursive` walk, collecting the regular files found in name order.
+#[derive(Debug, Default)]
+struct Walk {
+    follow_symlinks: bool,
+    /// Leave out `.git` and whatever the `.gitignore` files met on the way
+    /// ignore
+    respect_gitignore: bool,
+    /// Globs for the files to keep, for `--include`
+    include: Vec<String>,
+    /// Globs for the files and directories to leave out, for `--exclude`
+    exclude: Vec<String>,
+    /// The directory FILE being walked, which the globs are relative to
+    root: PathBuf,
+    /// Directories already walked, which keeps a followed link back up the
+    /// tree from looping
+    visited: HashSet<PathBuf>,
+    files: Vec<String>,
+    /// A directory couldn't be read or held a name that isn't UTF-8
+    failed: bool,
+}
+
+impl Walk {
+    /// Adds the regular files under `dir`, skipping the symbolic links inside
+    /// it unless `follow_symlinks` is set. `ignores` holds the `.gitignore`
+    /// files of the directories above.
+    fn dir(&mut self, dir: &Path, ignores: &mut Vec<Gitignore>) {
+        if let Ok(dir) = std::fs::canonicalize(dir) {
+            if !self.visited.insert(dir) {
+                return;
+            }
+        }
+        let entries = std::fs::read_dir(dir).and_then(|entries| {
+            let mut entries = entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.sort();
+            Ok(entries)
+        });
+        let entries = match entries {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("{}", io_error(&dir.display().to_string(), &e));
+                self.failed = true;
+                return;
+            }
+        };
+
+        let gitignore = self
+            .respect_gitignore
+            .then(|| Gitignore::read(dir))
+            .flatten();
+        let has_gitignore = gitignore.is_some();
+        ignores.extend(gitignore);
+        for path in entries {
+            let Ok(mut metadata) = std::fs::symlink_metadata(&path) else {
+                continue;
+            };
+            if metadata.is_symlink() {
+                match std::fs::metadata(&path) {
+                    Ok(target) if self.follow_symlinks => metadata = target,
+                    _ => continue,
+                }
+            }
+            if self.respect_gitignore
+                && (path.ends_with(".git") || Gitignore::ignores(ignores, &path, metadata.is_dir()))
+            {
+                continue;
+            }
+            if self.excludes(&path, metadata.is_dir()) {
+                continue;
+            }
+            if metadata.is_dir() {
+                self.dir(&path, ignores);
+            } else if metadata.is_file() {
+                match path.to_str() {
+                    Some(name) => self.files.push(name.to_string()),
+                    None => {
+                        eprintln!("wc: {}: file name is not valid UTF-8", path.display());
+                        self.failed = true;
+                    }
+                }
+            }
+        }
+        if has_gitignore {
+            ignores.pop();
+        }
+    }
+
+    /// Whether `path` is left out by `--exclude`, or is a file that isn't
+    /// kept by `--include`. Globs are matched like those of `--only`, but
+    /// against the path below the `root`.
+    fn excludes(&self, path: &Path, is_dir: bool) -> bool {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let parts: Vec<_> = relative.iter().map(|part| part.to_string_lossy()).collect();
+        let relative = parts.join("/");
+        let matches = |pattern: &String| only_matches(std::slice::from_ref(pattern), &relative);
+        self.exclude.iter().any(matches)
+            || !is_dir && !self.include.is_empty() && !self.include.iter().any(matches)
+    }
+}
+
+/// The patterns of a `.gitignore` file, which apply below its directory.
+#[derive(Debug)]
+struct Gitignore {
+    dir: PathBuf,
+    patterns: Vec<IgnorePattern>,
+}
+
+impl Gitignore {
+    fn read(dir: &Path) -> Option<Self> {
+        let text = std::fs::read_to_string(dir.join(".gitignore")).ok()?;
+        Some(Gitignore {
+            dir: dir.to_path_buf(),
+            patterns: text.lines().filter_map(IgnorePattern::parse).collect(),
+        })
+    }
+
+    /// Whether `path` is ignored by the deepest of `ignores` with a pattern
+    /// for it, where the last matching pattern of a file wins.
+    fn ignores(ignores: &[Gitignore], path: &Path, is_dir: bool) -> bool {
+        ignores
+            .iter()
+            .rev()
+            .find_map(|gitignore| {
+                let relative = path.strip_prefix(&gitignore.dir).ok()?;
+                let parts: Vec<&str> = relative
+                    .iter()
+                    .map(|part| part.to_str())
+                    .collect::<Option<_>>()?;
+                let pattern = gitignore
+                    .patterns
+                    .iter()
+                    .rev()
+                    .find(|p| p.matches(&parts, is_dir))?;
+                Some(!pattern.negated)
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// A line of a `.gitignore` file.
+#[derive(Debug)]
+struct IgnorePattern {
+    /// The `/`-separated parts of the pattern
+    parts: Vec<String>,
+    /// A pattern holding a `/` other than a trailing one is matched against
+    /// the whole path below the `.gitignore`, others against the name only
+    anchored: bool,
+    /// Starts with `!`, so it re-includes what an earlier pattern ignored
+    negated: bool,
+    /// Ends with `/`, so it only matches directories
+    dir_only: bool,
+}
+
+impl IgnorePattern {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let negated = line.starts_with('!');
+        let line = line.strip_prefix('!').unwrap_or(line);
+        let dir_only = line.ends_with('/');
+        let line = line.trim_end_matches('/');
+        Some(IgnorePattern {
+            parts: line
+                .trim_start_matches('/')
+                .split('/')
+                .map(String::from)
+                .collect(),
+            anchored: line.contains('/'),
+            negated,
+            dir_only,
+        })
+    }
+
+    fn matches(&self, parts: &[&str], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            false
+        } else if self.anchored {
+            match_parts(&self.parts, parts)
+        } else {
+            parts
+                .last()
+                .is_some_and(|name| glob_match(&self.parts[0], name))
+        }
+    }
+}
+
+/// Matches the parts of a path against those of a pattern, where a `**`
+/// part stands for any number of parts.
+fn match_parts(pattern: &[String], parts: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => parts.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=parts.len()).any(|skip| match_parts(rest, &parts[skip..]))
+        }
+        Some((first, rest)) => parts
+            .split_first()
+            .is_some_and(|(part, parts)| glob_match(first, part) && match_parts(rest, parts)),
+    }
+}
+
+/// Parses a positive, possibly fractional, number of seconds.
+fn parse_seconds(secs: &str) -> Option<Duration> {
+    let secs = secs.parse::<f64>().ok()?;
+    (secs.is_finite() && secs > 0.0).then(|| Duration::from_secs_f64(secs))
+}
+
+/// Parses a duration such as `2s`, `500ms`, `1m` or `1h`, where a bare number
+/// is seconds.
+fn parse_duration(duration: &str) -> Option<Duration> {
+    let (number, unit) = match duration.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(i) => duration.split_at(i),
+        None => (duration, "s"),
+    };
+    let scale = match unit {
+        "ms" => 0.001,
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => return None,
+    };
+    let duration = parse_seconds(number)?.mul_f64(scale);
+    (!duration.is_zero()).then_some(duration)
+}
+
+/// Keeps only the lines inside sections that open with a line matching `start`
+/// and close with a line matching `end`. The marker lines themselves are
+/// dropped, and a section that is never closed runs to the end of the input.
+fn section_text(input: &str, start: &Regex, end: &Regex) -> String {
+    let mut text = String::new();
+    let mut in_section = false;
+    for line in input.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        if !in_section {
+            in_section = start.is_match(content);
+        } else if end.is_match(content) {
+            in_section = false;
+        } else {
+            text.push_str(line);
+        }
+    }
+    text
+}
+
+/// How comments and string literals are written in a language.
+#[derive(Debug)]
+struct CommentSyntax {
+    line: &'static str,
+    block: Option<(&'static str, &'static str)>,
+    /// Whether block comments nest, as in Rust
+    nested: bool,
+    quotes: &'static [char],
+    /// Whether backslash escapes a quote inside a string
+    escapes: bool,
+    /// Whether `'x'` is a character literal rather than a string
+    char_literals: bool,
+    /// Whether a line comment must start a word, as with `#` in shell
+    word_start: bool,
+}
+
+impl CommentSyntax {
+    fn for_language(lang: &str) -> Option<&'static Self> {
+        const RUST: CommentSyntax = CommentSyntax {
+            line: "//",
+            block: Some(("/*", "*/")),
+            nested: true,
+            quotes: &['"'],
+            escapes: true,
+            char_literals: true,
+            word_start: false,
+        };
+        const C: CommentSyntax = CommentSyntax {
+            nested: false,
+            ..RUST
+        };
+        const PYTHON: CommentSyntax = CommentSyntax {
+            line: "#",
+            block: None,
+            nested: false,
+            quotes: &['"', '\''],
+            escapes: true,
+            char_literals: false,
+            word_start: false,
+        };
+        const SHELL: CommentSyntax = CommentSyntax {
+            word_start: true,
+            ..PYTHON
+        };
+
+        match lang {
+            "rust" => Some(&RUST),
+            "c" => Some(&C),
+            "python" => Some(&PYTHON),
+            "shell" => Some(&SHELL),
+            _ => None,
+        }
+    }
+
+    /// Removes comments from `input`, leaving string literals alone. Lines
+    /// left blank by the removal of a comment are dropped entirely.
+    fn strip(&self, input: &str) -> String {
+        let mut text = String::with_capacity(input.len());
+        let mut line = String::new();
+        let mut had_comment = false;
+        let mut depth = 0;
+        let mut rest = input;
+
+        let mut end_line = |line: &mut String, had_comment: &mut bool, newline: bool| {
+            if !(*had_comment && line.trim().is_empty()) {
+                text.push_str(line);
+                if newline {
+                    text.push('\n');
+                }
+            }
+            line.clear();
+            *had_comment = false;
+        };
+
+        while let Some(c) = rest.chars().next() {
+            if depth > 0 {
+                let (open, close) = self.block.unwrap();
+                if rest.starts_with(close) {
+                    depth -= 1;
+                    rest = &rest[close.len()..];
+                } else if self.nested && rest.starts_with(open) {
+                    depth += 1;
+                    rest = &rest[open.len()..];
+                } else {
+                    if c == '\n' {
+                        end_line(&mut line, &mut had_comment, true);
+                        had_comment = true;
+                    }
+                    rest = &rest[c.len_utf8()..];
+                }
+                continue;
+            }
+
+            let at_word_start = line.chars().last().is_none_or(char::is_whitespace);
+            if rest.starts_with(self.line) && (!self.word_start || at_word_start) {
+                had_comment = true;
+                rest = rest.find('\n').map_or("", |end| &rest[end..]);
+            } else if let Some((open, _)) = self.block.filter(|(open, _)| rest.starts_with(open)) {
+                had_comment = true;
+                depth = 1;
+                rest = &rest[open.len()..];
+            } else if self.quotes.contains(&c) || (self.char_literals && c == '\'') {
+                let len = self.literal_len(rest);
+                line.push_str(&rest[..len]);
+                rest = &rest[len..];
+            } else if c == '\n' {
+                end_line(&mut line, &mut had_comment, true);
+                rest = &rest[1..];
+            } else {
+                line.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        end_line(&mut line, &mut had_comment, false);
+        text
+    }
+
+    /// The byte length of the string or character literal at the start of
+    /// `input`. A `'` that doesn't form a character literal (such as a Rust
+    /// lifetime) is a single byte, and an unterminated string runs to the end.
+    fn literal_len(&self, input: &str) -> usize {
+        let quote = input.chars().next().unwrap();
+        if quote == '\'' && self.char_literals {
+            let mut chars = input.char_indices().skip(1);
+            let len = match chars.next() {
+                Some((_, '\\')) => chars.nth(1).filter(|&(_, c)| c == '\''),
+                Some(_) => chars.next().filter(|&(_, c)| c == '\''),
+                None => None,
+            };
+            return len.map_or(1, |(i, _)| i + 1);
+        }
+
+        let mut escaped = false;
+        for (i, c) in input.char_indices().skip(1) {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' && self.escapes {
+                escaped = true;
+            } else if c == quote {
+                return i + 1;
+            }
+        }
+        input.len()
+    }
+}
+
+/// Parses a range of hexadecimal code points such as `4E00-9FFF`, with each
+/// end optionally written as `U+XXXX`.
+fn parse_char_range(range: &str) -> Option<(char, char)> {
+    let code_point = |hex: &str| {
+        let hex = hex
+            .strip_prefix("U+")
+            .or(hex.strip_prefix("u+"))
+            .unwrap_or(hex);
+        u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+    };
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (code_point(start)?, code_point(end)?);
+    (start <= end).then_some((start, end))
+}
+
+/// Parses a `START-END` column range, where `1 <= START <= END`.
+fn parse_column_range(range: &str) -> Option<(usize, usize)> {
+    let (start, end) = range.split_once('-')?;
+    let start = start.parse::<usize>().ok()?;
+    let end = end.parse::<usize>().ok()?;
+    (start >= 1 && start <= end).then_some((start, end))
+}
+
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// Per-type bracket counts and the first bracket that breaks the nesting.
+#[derive(Debug, Default)]
+struct BracketReport {
+    open: [u64; 3],
+    close: [u64; 3],
+    /// The first unmatched bracket and its byte offset in the input
+    unmatched: Option<(char, usize)>,
+    /// Number of inputs with unbalanced brackets, used for the total
+    unbalanced: usize,
+}
+
+impl BracketReport {
+    /// Scans `input` for brackets. With `skip_strings`, anything between a pair
+    /// of `"` or `'` quotes is ignored; this is naive and does not understand
+    /// comments, raw strings or apostrophes used outside of string literals.
+    fn parse(input: &str, skip_strings: bool) -> Self {
+        let mut report = BracketReport::default();
+        let mut stack: Vec<(usize, char, usize)> = Vec::new();
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+
+        for (offset, c) in input.char_indices() {
+            if let Some(q) = quote {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+                continue;
+            }
+            if skip_strings && (c == '"' || c == '\'') {
+                quote = Some(c);
+                continue;
+            }
+
+            if let Some(kind) = BRACKETS.iter().position(|&(open, _)| open == c) {
+                report.open[kind] += 1;
+                stack.push((kind, c, offset));
+            } else if let Some(kind) = BRACKETS.iter().position(|&(_, close)| close == c) {
+                report.close[kind] += 1;
+                match stack.last() {
+                    Some(&(top, _, _)) if top == kind => {
+                        stack.pop();
+                    }
+                    _ if report.unmatched.is_none() => report.unmatched = Some((c, offset)),
+                    _ => {}
+                }
+            }
+        }
+
+        if report.unmatched.is_none() {
+            report.unmatched = stack.first().map(|&(_, c, offset)| (c, offset));
+        }
+        if report.unmatched.is_some() {
+            report.unbalanced = 1;
+        }
+        report
+    }
+
+    fn add(&mut self, other: &BracketReport) {
+        for kind in 0..BRACKETS.len() {
+            self.open[kind] += other.open[kind];
+            self.close[kind] += other.close[kind];
+        }
+        self.unbalanced += other.unbalanced;
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str, is_total: bool) -> io::Result<()> {
+        let counts = BRACKETS
+            .iter()
+            .enumerate()
+            .map(|(kind, (open, close))| {
+                format!("{}{} {}/{}", open, close, self.open[kind], self.close[kind])
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let status = match self.unmatched {
+            _ if is_total && self.unbalanced > 0 => format!("{} unbalanced", self.unbalanced),
+            Some((c, offset)) => format!("unmatched '{}' at byte {}", c, offset),
+            None => String::from("balanced"),
+        };
+        writeln!(out, "{}: brackets {}, {}", filename, counts, status)
+    }
+}
+
+/// Character and word counts within fixed-width column ranges, summed over lines.
+#[derive(Debug, Default)]
+struct ColumnReport {
+    ranges: Vec<(usize, usize)>,
+    chars: Vec<u64>,
+    words: Vec<u64>,
+}
+
+impl ColumnReport {
+    /// Counts each range on every line. Lines shorter than a range contribute
+    /// whatever part of the range they do cover.
+    fn parse(input: &str, ranges: &[(usize, usize)]) -> Self {
+        let mut chars = vec![0; ranges.len()];
+        let mut words = vec![0; ranges.len()];
+
+        for line in input.lines() {
+            for (i, &(start, end)) in ranges.iter().enumerate() {
+                let field: String = line.chars().skip(start - 1).take(end - start + 1).collect();
+                chars[i] += field.chars().count() as u64;
+                words[i] += field.split_whitespace().count() as u64;
+            }
+        }
+
+        ColumnReport {
+            ranges: ranges.to_vec(),
+            chars,
+            words,
+        }
+    }
+
+    fn add(&mut self, other: &ColumnReport) {
+        if self.ranges.is_empty() {
+            self.ranges = other.ranges.clone();
+            self.chars = vec![0; other.ranges.len()];
+            self.words = vec![0; other.ranges.len()];
+        }
+        for i in 0..self.ranges.len() {
+            self.chars[i] += other.chars[i];
+            self.words[i] += other.words[i];
+        }
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str) -> io::Result<()> {
+        for (i, (start, end)) in self.ranges.iter().enumerate() {
+            writeln!(
+                out,
+                "{}: columns {}-{} chars {} words {}",
+                filename, start, end, self.chars[i], self.words[i]
+            )?;
+        }
+        Ok(())
+    }
+}
+
+const TODO_MARKERS: [&str; 4] = ["TODO", "FIXME", "XXX", "HACK"];
+
+/// Occurrences of each tech-debt marker, and where they were found.
+#[derive(Debug, Default)]
+struct TodoReport {
+    counts: [u64; TODO_MARKERS.len()],
+    /// Line number and marker of every match, in input order
+    matches: Vec<(usize, &'static str)>,
+}
+
+impl TodoReport {
+    fn parse(input: &str) -> Self {
+        let mut report = TodoReport::default();
+        for (number, line) in input.lines().enumerate() {
+            let mut found = Vec::new();
+            for (i, marker) in TODO_MARKERS.iter().enumerate() {
+                for (pos, _) in line.match_indices(marker) {
+                    report.counts[i] += 1;
+                    found.push((pos, *marker));
+                }
+            }
+            found.sort();
+            report
+                .matches
+                .extend(found.into_iter().map(|(_, marker)| (number + 1, marker)));
+        }
+        report
+    }
+
+    fn add(&mut self, other: &TodoReport) {
+        for i in 0..TODO_MARKERS.len() {
+            self.counts[i] += other.counts[i];
+        }
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str, list: bool) -> io::Result<()> {
+        let counts = TODO_MARKERS
+            .iter()
+            .zip(self.counts)
+            .map(|(marker, count)| format!("{} {}", marker, count))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(out, "{}: {}", filename, counts)?;
+        if list {
+            for (line, marker) in &self.matches {
+                writeln!(out, "{}:{}: {}", filename, line, marker)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+static URL_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"https?://[^\s<>"'()\[\]{}]+"#).unwrap());
+
+/// Occurrences of each distinct http(s) URL.
+#[derive(Debug, Default)]
+struct UrlReport {
+    urls: BTreeMap<String, u64>,
+}
+
+impl UrlReport {
+    fn parse(input: &str) -> Self {
+        let mut report = UrlReport::default();
+        for url in URL_PATTERN.find_iter(input) {
+            // Punctuation directly after a URL usually ends the sentence
+            let url = url
+                .as_str()
+                .trim_end_matches(['.', ',', ';', ':', '!', '?']);
+            *report.urls.entry(url.to_string()).or_default() += 1;
+        }
+        report
+    }
+
+    fn add(&mut self, other: &UrlReport) {
+        for (url, count) in &other.urls {
+            *self.urls.entry(url.clone()).or_default() += count;
+        }
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str, list: bool) -> io::Result<()> {
+        let total: u64 = self.urls.values().sum();
+        writeln!(
+            out,
+            "{}: urls {} distinct {}",
+            filename,
+            total,
+            self.urls.len()
+        )?;
+        if list {
+            for url in self.urls.keys() {
+                writeln!(out, "{}: {}", filename, url)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Occurrences of each XML element name, and the attributes on those elements.
+#[derive(Debug, Default)]
+struct XmlReport {
+    elements: BTreeMap<String, u64>,
+    attributes: u64,
+    /// Why and where reading stopped early, if the input is malformed
+    error: Option<String>,
+}
+
+impl XmlReport {
+    /// Counts the XML in `input` as it's read, holding only one event at a
+    /// time in memory. Fails only if `input` can't be read; malformed XML is
+    /// recorded in `error` instead.
+    fn read(input: impl BufRead) -> io::Result<Self> {
+        let mut report = XmlReport::default();
+        let mut reader = Reader::from_reader(input);
+        let mut event = Vec::new();
+        loop {
+            match reader.read_event_into(&mut event) {
+                Ok(XmlEvent::Start(element) | XmlEvent::Empty(element)) => {
+                    let name = element.name().as_ref().to_string();
+                    *report.elements.entry(name).or_default() += 1;
+                    report.attributes += element.attributes().count() as u64;
+                }
+                Ok(XmlEvent::Eof) => break,
+                Ok(_) => {}
+                Err(quick_xml::Error::Io(e)) => {
+                    return Err(Arc::try_unwrap(e)
+                        .unwrap_or_else(|e| io::Error::new(e.kind(), e.to_string())));
+                }
+                Err(e) => {
+                    // Keep what was counted up to the error
+                    report.error = Some(format!("at byte {}: {}", reader.error_position(), e));
+                    break;
+                }
+            }
+            event.clear();
+        }
+        Ok(report)
+    }
+
+    fn add(&mut self, other: &XmlReport) {
+        for (name, count) in &other.elements {
+            *self.elements.entry(name.clone()).or_default() += count;
+        }
+        self.attributes += other.attributes;
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str, list: bool) -> io::Result<()> {
+        let total: u64 = self.elements.values().sum();
+        writeln!(
+            out,
+            "{}: elements {} distinct {} attributes {}",
+            filename,
+            total,
+            self.elements.len(),
+            self.attributes
+        )?;
+        if list {
+            for (name, count) in &self.elements {
+                writeln!(out, "{}: {} {}", filename, name, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Number of JSON values of each type, counted in one pass over the input.
+#[derive(Debug, Default, Clone)]
+struct JsonReport {
+    objects: u64,
+    arrays: u64,
+    strings: u64,
+    numbers: u64,
+    booleans: u64,
+    nulls: u64,
+    /// Where and why the input stopped being valid JSON, if it did
+    error: Option<String>,
+}
+
+impl JsonReport {
+    fn parse(input: &str) -> Self {
+        let mut scanner = JsonScanner::default();
+        scanner.update(input.as_bytes());
+        scanner.finish()
+    }
+
+    fn add(&mut self, other: &JsonReport) {
+        self.objects += other.objects;
+        self.arrays += other.arrays;
+        self.strings += other.strings;
+        self.numbers += other.numbers;
+        self.booleans += other.booleans;
+        self.nulls += other.nulls;
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "{}: json objects {} arrays {} strings {} numbers {} booleans {} nulls {}",
+            filename,
+            self.objects,
+            self.arrays,
+            self.strings,
+            self.numbers,
+            self.booleans,
+            self.nulls
+        )
+    }
+}
+
+/// Counts the values of a JSON text without building them, as it arrives in
+/// chunks of any size. Everything needed to carry on, including the offset,
+/// is kept from one chunk to the next, so memory only grows with nesting.
+#[derive(Debug, Default, Clone)]
+struct JsonScanner {
+    report: JsonReport,
+    /// The open containers, innermost last: true for an object
+    open: Vec<bool>,
+    state: JsonState,
+    /// The offset of the next byte
+    offset: usize,
+    /// The offset of the first byte that broke the grammar, and what was
+    /// expected there; nothing after it is looked at
+    error: Option<(usize, &'static str)>,
+}
+
+/// Where a `JsonScanner` is in the grammar.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum JsonState {
+    /// Before a value
+    #[default]
+    Value,
+    /// Just after `[`, where `]` may close the array
+    ArrayStart,
+    /// Just after `{`, where `}` may close the object
+    ObjectStart,
+    /// Before an object key
+    Key,
+    /// Inside a string, which is a key when `key` holds
+    String { key: bool },
+    /// Just after a backslash inside a string
+    Escape { key: bool },
+    /// Inside a `\u` escape starting at `at`, with `left` hex digits to come
+    Unicode { key: bool, at: usize, left: u8 },
+    /// Between an object key and its colon
+    Colon,
+    /// Inside a number, at `part`
+    Number(NumberPart),
+    /// Inside `true`, `false` or `null` starting at `at`, with `rest` to come
+    Literal { rest: &'static [u8], at: usize },
+    /// After a value, before a separator, a closing bracket or the end
+    After,
+}
+
+/// The parts of a JSON number, each named for what its last byte was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberPart {
+    Minus,
+    Zero,
+    Integer,
+    Point,
+    Fraction,
+    Exponent,
+    ExponentSign,
+    ExponentDigits,
+}
+
+impl JsonScanner {
+    /// Scans the next chunk of the input.
+    fn update(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            if self.error.is_some() {
+                return;
+            }
+            if let Err(expected) = self.push(b) {
+                self.error = Some((self.offset, expected));
+                return;
+            }
+            self.offset += 1;
+        }
+    }
+
+    /// Moves past one byte, failing with what was expected instead of it.
+    fn push(&mut self, b: u8) -> Result<(), &'static str> {
+        let whitespace = matches!(b, b' ' | b'\t' | b'\n' | b'\r');
+        match self.state {
+            JsonState::Value
+            | JsonState::ArrayStart
+            | JsonState::ObjectStart
+            | JsonState::Key
+            | JsonState::Colon
+            | JsonState::After
+                if whitespace => {}
+            JsonState::ArrayStart if b == b']' => self.state = JsonState::After,
+            JsonState::ArrayStart => {
+                self.open.push(false);
+                self.state = JsonState::Value;
+                return self.push(b);
+            }
+            JsonState::ObjectStart if b == b'}' => self.state = JsonState::After,
+            JsonState::ObjectStart => {
+                self.open.push(true);
+                self.state = JsonState::Key;
+                return self.push(b);
+            }
+            JsonState::Key if b == b'"' => self.state = JsonState::String { key: true },
+            JsonState::Key => return Err("a string key"),
+            JsonState::Colon if b == b':' => self.state = JsonState::Value,
+            JsonState::Colon => return Err("':'"),
+            JsonState::Value => self.value(b)?,
+            JsonState::String { key } => match b {
+                b'"' if key => self.state = JsonState::Colon,
+                b'"' => self.state = JsonState::After,
+                b'\\' => self.state = JsonState::Escape { key },
+                0..=0x1f => return Err("an escaped control character"),
+                _ => {}
+            },
+            JsonState::Escape { key } => match b {
+                b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't' => {
+                    self.state = JsonState::String { key }
+                }
+                b'u' => {
+                    let at = self.offset;
+                    self.state = JsonState::Unicode { key, at, left: 4 };
+                }
+                _ => return Err("an escape sequence"),
+            },
+            JsonState::Unicode { key, at, left } => {
+                if !b.is_ascii_hexdigit() {
+                    // Reported where the escape starts, as the digits are part of it
+                    self.error = Some((at, "an escape sequence"));
+                    return Ok(());
+                }
+                self.state = match left {
+                    1 => JsonState::String { key },
+                    _ => JsonState::Unicode {
+                        key,
+                        at,
+                        left: left - 1,
+                    },
+                };
+            }
+            JsonState::Number(part) => {
+                let next = match (part, b) {
+                    (NumberPart::Minus, b'0') => NumberPart::Zero,
+                    (NumberPart::Minus | NumberPart::Integer, b'0'..=b'9') => NumberPart::Integer,
+                    (NumberPart::Zero | NumberPart::Integer, b'.') => NumberPart::Point,
+                    (NumberPart::Point | NumberPart::Fraction, b'0'..=b'9') => NumberPart::Fraction,
+                    (
+                        NumberPart::Zero | NumberPart::Integer | NumberPart::Fraction,
+                        b'e' | b'E',
+                    ) => NumberPart::Exponent,
+                    (NumberPart::Exponent, b'+' | b'-') => NumberPart::ExponentSign,
+                    (
+                        NumberPart::Exponent
+                        | NumberPart::ExponentSign
+                        | NumberPart::ExponentDigits,
+                        b'0'..=b'9',
+                    ) => NumberPart::ExponentDigits,
+                    _ if part.needs_digit() => return Err("a digit"),
+                    // The number ended just before this byte
+                    _ => {
+                        self.state = JsonState::After;
+                        return self.push(b);
+                    }
+                };
+                self.state = JsonState::Number(next);
+            }
+            JsonState::Literal { rest, at } => {
+                if rest[0] != b {
+                    self.error = Some((at, "a value"));
+                    return Ok(());
+                }
+                if rest.len() > 1 {
+                    self.state = JsonState::Literal {
+                        rest: &rest[1..],
+                        at,
+                    };
+                } else {
+                    match b {
+                        b'e' => self.report.booleans += 1,
+                        _ => self.report.nulls += 1,
+                    }
+                    self.state = JsonState::After;
+                }
+            }
+            JsonState::After => match (self.open.last(), b) {
+                (None, _) => return Err("the end of the input"),
+                (Some(true), b',') => self.state = JsonState::Key,
+                (Some(false), b',') => self.state = JsonState::Value,
+                (Some(true), b'}') | (Some(false), b']') => {
+                    self.open.pop();
+                }
+                (Some(true), _) => return Err("',' or '}'"),
+                (Some(false), _) => return Err("',' or ']'"),
+            },
+        }
+        Ok(())
+    }
+
+    /// Starts the value that `b` begins.
+    fn value(&mut self, b: u8) -> Result<(), &'static str> {
+        let at = self.offset;
+        self.state = match b {
+            b'{' => {
+                self.report.objects += 1;
+                JsonState::ObjectStart
+            }
+            b'[' => {
+                self.report.arrays += 1;
+                JsonState::ArrayStart
+            }
+            b'"' => {
+                self.report.strings += 1;
+                JsonState::String { key: false }
+            }
+            b'-' | b'0'..=b'9' => {
+                self.report.numbers += 1;
+                match b {
+                    b'-' => JsonState::Number(NumberPart::Minus),
+                    b'0' => JsonState::Number(NumberPart::Zero),
+                    _ => JsonState::Number(NumberPart::Integer),
+                }
+            }
+            b't' => JsonState::Literal { rest: b"rue", at },
+            b'f' => JsonState::Literal { rest: b"alse", at },
+            b'n' => JsonState::Literal { rest: b"ull", at },
+            _ => return Err("a value"),
+        };
+        Ok(())
+    }
+
+    /// The counts of the whole input, with what it was missing at the end if
+    /// it stopped short.
+    fn finish(self) -> JsonReport {
+        let end = self.offset;
+        let error = self.error.or(match self.state {
+            JsonState::Value | JsonState::ArrayStart => Some((end, "a value")),
+            JsonState::ObjectStart | JsonState::Key => Some((end, "a string key")),
+            JsonState::String { .. } => Some((end, "'\"'")),
+            JsonState::Escape { .. } => Some((end, "an escape sequence")),
+            JsonState::Unicode { at, .. } => Some((at, "an escape sequence")),
+            JsonState::Colon => Some((end, "':'")),
+            JsonState::Number(part) if part.needs_digit() => Some((end, "a digit")),
+            JsonState::Literal { at, .. } => Some((at, "a value")),
+            JsonState::Number(_) | JsonState::After => match self.open.last() {
+                None => None,
+                Some(true) => Some((end, "',' or '}'")),
+                Some(false) => Some((end, "',' or ']'")),
+            },
+        });
+        JsonReport {
+            error: error
+                .map(|(offset, expected)| format!("at byte {}: expected {}", offset, expected)),
+            ..self.report
+        }
+    }
+}
+
+impl NumberPart {
+    /// Whether the number can't end here.
+    fn needs_digit(self) -> bool {
+        matches!(
+            self,
+            NumberPart::Minus | NumberPart::Point | NumberPart::Exponent | NumberPart::ExponentSign
+        )
+    }
+}
+
+/// Number of Markdown code blocks by kind, and the lines of code inside them.
+#[derive(Debug, Default)]
+struct CodeBlockReport {
+    fenced: u64,
+    indented: u64,
+    lines: u64,
+}
+
+impl CodeBlockReport {
+    fn parse(input: &str) -> Self {
+        let mut report = CodeBlockReport::default();
+        let mut in_code_block = false;
+
+        for event in Parser::new(input) {
+            match event {
+                Event::Start(Tag::CodeBlock(kind)) => {
+                    in_code_block = true;
+                    match kind {
+                        CodeBlockKind::Fenced(_) => report.fenced += 1,
+                        CodeBlockKind::Indented => report.indented += 1,
+                    }
+                }
+                Event::End(TagEnd::CodeBlock) => in_code_block = false,
+                Event::Text(text) if in_code_block => report.lines += text.lines().count() as u64,
+                _ => {}
+            }
+        }
+        report
+    }
+
+    fn add(&mut self, other: &CodeBlockReport) {
+        self.fenced += other.fenced;
+        self.indented += other.indented;
+        self.lines += other.lines;
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "{}: code blocks {} (fenced {}, indented {}) lines {}",
+            filename,
+            self.fenced + self.indented,
+            self.fenced,
+            self.indented,
+            self.lines
+        )
+    }
+}
+
+/// Physical lines, and logical lines once those ending in a backslash are
+/// joined to the next one, as in shell scripts and Makefiles.
+#[derive(Debug, Default)]
+struct ContinuationReport {
+    physical: u64,
+    logical: u64,
+}
+
+impl ContinuationReport {
+    fn parse(input: &str) -> Self {
+        let mut report = ContinuationReport::default();
+        let mut continued = false;
+        for line in input.lines() {
+            report.physical += 1;
+            // An even run of backslashes is escaped backslashes, not a continuation
+            let backslashes = line.len() - line.trim_end_matches('\\').len();
+            continued = backslashes % 2 == 1;
+            if !continued {
+                report.logical += 1;
+            }
+        }
+        // A continuation on the last line still ends a logical line
+        if continued {
+            report.logical += 1;
+        }
+        report
+    }
+
+    fn add(&mut self, other: &ContinuationReport) {
+        self.physical += other.physical;
+        self.logical += other.logical;
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "{}: physical lines {} logical lines {}",
+            filename, self.physical, self.logical
+        )
+    }
+}
+
+/// Where the widest line is, for `-LL`. The total keeps the widest of all
+/// the files, and the first of them on a tie.
+#[derive(Debug, Clone)]
+struct LongestLine {
+    filename: String,
+    line: u64,
+    width: u64,
+}
+
+impl LongestLine {
+    fn add(this: &mut Option<LongestLine>, other: &LongestLine) {
+        if this
+            .as_ref()
+            .is_none_or(|longest| other.width > longest.width)
+        {
+            *this = Some(other.clone());
+        }
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str, args: &Args) -> io::Result<()> {
+        let file = if self.filename.is_empty() {
+            Cow::Borrowed(display_name(&self.filename))
+        } else {
+            args.quoting.quote(&self.filename)
+        };
+        writeln!(
+            out,
+            "{}: longest line {}:{} ({} columns)",
+            filename, file, self.line, self.width
+        )
+    }
+}
+
+/// Lines by how they end, for `--line-endings`.
+#[derive(Debug, Default)]
+struct LineEndingReport {
+    lf: u64,
+    crlf: u64,
+    /// A CR not followed by LF, as in classic Mac OS files
+    cr: u64,
+}
+
+impl LineEndingReport {
+    fn parse(input: &[u8]) -> Self {
+        let mut report = LineEndingReport::default();
+        for i in memchr::memchr2_iter(b'\n', b'\r', input) {
+            match input[i] {
+                b'\r' if input.get(i + 1) == Some(&b'\n') => report.crlf += 1,
+                b'\r' => report.cr += 1,
+                // The LF of a CRLF was counted with its CR
+                _ if i > 0 && input[i - 1] == b'\r' => {}
+                _ => report.lf += 1,
+            }
+        }
+        report
+    }
+
+    /// Whether more than one kind of line ending occurs.
+    fn mixed(&self) -> bool {
+        [self.lf, self.crlf, self.cr]
+            .iter()
+            .filter(|&&count| count > 0)
+            .count()
+            > 1
+    }
+
+    fn add(&mut self, other: &LineEndingReport) {
+        self.lf += other.lf;
+        self.crlf += other.crlf;
+        self.cr += other.cr;
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str) -> io::Result<()> {
+        let mixed = if self.mixed() { " (mixed)" } else { "" };
+        writeln!(
+            out,
+            "{}: line endings LF {} CRLF {} CR {}{}",
+            filename, self.lf, self.crlf, self.cr, mixed
+        )
+    }
+}
+
+/// Number of characters encoded with each UTF-8 length, indexed by length - 1.
+#[derive(Debug, Default)]
+struct Utf8Report {
+    lengths: [u64; 4],
+}
+
+impl Utf8Report {
+    fn parse(input: &str) -> Self {
+        let mut report = Utf8Report::default();
+        for c in input.chars() {
+            report.lengths[c.len_utf8() - 1] += 1;
+        }
+        report
+    }
+
+    fn add(&mut self, other: &Utf8Report) {
+        for (length, count) in self.lengths.iter_mut().zip(other.lengths) {
+            *length += count;
+        }
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str) -> io::Result<()> {
+        let lengths = self
+            .lengths
+            .iter()
+            .enumerate()
+            .map(|(i, count)| format!("{}-byte {}", i + 1, count))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(out, "{}: utf8 {}", filename, lengths)
+    }
+}
+
+/// What of an input isn't ASCII, for `--ascii`.
+#[derive(Debug, Default)]
+struct AsciiReport {
+    bytes: u64,
+    chars: u64,
+    /// The 1-based number of the first line that isn't all ASCII
+    first_line: Option<u64>,
+}
+
+impl AsciiReport {
+    fn parse(raw: &[u8], input: &str, delimiter: char) -> Self {
+        let first = raw.iter().position(|b| !b.is_ascii());
+        AsciiReport {
+            bytes: raw.iter().filter(|b| !b.is_ascii()).count() as u64,
+            chars: input.chars().filter(|c| !c.is_ascii()).count() as u64,
+            first_line: first
+                .map(|i| memchr::memchr_iter(delimiter as u8, &raw[..i]).count() as u64 + 1),
+        }
+    }
+
+    fn add(&mut self, other: &AsciiReport) {
+        self.bytes += other.bytes;
+        self.chars += other.chars;
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "{}: non-ASCII bytes {} chars {}",
+            filename, self.bytes, self.chars
+        )
+    }
+}
+
+/// Characters by class, for `--classify`. Every character is in exactly one
+/// class, the first of these that it fits.
+#[derive(Debug, Default)]
+struct ClassReport {
+    letters: u64,
+    digits: u64,
+    punctuation: u64,
+    whitespace: u64,
+    control: u64,
+    other: u64,
+}
+
+impl ClassReport {
+    fn parse(input: &str) -> Self {
+        let mut report = ClassReport::default();
+        for c in input.chars() {
+            let class = if c.is_alphabetic() {
+                &mut report.letters
+            } else if c.is_numeric() {
+                &mut report.digits
+            } else if is_punctuation(c) {
+                &mut report.punctuation
+            } else if c.is_whitespace() {
+                &mut report.whitespace
+            } else if c.is_control() {
+                &mut report.control
+            } else {
+                &mut report.other
+            };
+            *class += 1;
+        }
+        report
+    }
+
+    fn add(&mut self, other: &ClassReport) {
+        self.letters += other.letters;
+        self.digits += other.digits;
+        self.punctuation += other.punctuation;
+        self.whitespace += other.whitespace;
+        self.control += other.control;
+        self.other += other.other;
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "{}: letters {} digits {} punctuation {} whitespace {} control {} other {}",
+            filename,
+            self.letters,
+            self.digits,
+            self.punctuation,
+            self.whitespace,
+            self.control,
+            self.other
+        )
+    }
+}
+
+/// Whether `c` is ASCII punctuation or in the General Punctuation or CJK
+/// Symbols and Punctuation blocks, which hold the punctuation of most text.
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || ('\u{2010}'..='\u{2027}').contains(&c)
+        || ('\u{2030}'..='\u{205E}').contains(&c)
+        || ('\u{3001}'..='\u{3003}').contains(&c)
+        || ('\u{3008}'..='\u{3011}').contains(&c)
+        || matches!(c, '¡' | '§' | '«' | '¶' | '·' | '»' | '¿')
+}
+
+/// How often each length occurs, enough to summarise the lengths without
+/// keeping them all.
+#[derive(Debug, Default)]
+struct Lengths {
+    counts: BTreeMap<u64, u64>,
+}
+
+impl Lengths {
+    fn record(&mut self, length: usize) {
+        *self.counts.entry(length as u64).or_default() += 1;
+    }
+
+    fn add(&mut self, other: &Lengths) {
+        for (&length, count) in &other.counts {
+            *self.counts.entry(length).or_default() += count;
+        }
+    }
+
+    /// The `n`th smallest length, counting from 0.
+    fn nth(&self, n: u64) -> u64 {
+        let mut seen = 0;
+        for (&length, &count) in &self.counts {
+            seen += count;
+            if seen > n {
+                return length;
+            }
+        }
+        unreachable!("n is less than the number of lengths")
+    }
+
+    fn summary(&self, sep: char) -> String {
+        let (Some((&min, _)), Some((&max, _))) =
+            (self.counts.first_key_value(), self.counts.last_key_value())
+        else {
+            return String::from("none");
+        };
+        let n: u64 = self.counts.values().sum();
+        let sum: u64 = self
+            .counts
+            .iter()
+            .map(|(length, count)| length * count)
+            .sum();
+        let median = (self.nth((n - 1) / 2) + self.nth(n / 2)) as f64 / 2.0;
+        format!(
+            "min {} max {} mean {} median {}",
+            min,
+            max,
+            decimal(sum as f64 / n as f64, 1, sep),
+            decimal(median, 1, sep)
+        )
+    }
+}
+
+/// What `--histogram` buckets by length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Histogram {
+    LineLength,
+    WordLength,
+}
+
+impl Histogram {
+    fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "line-length" => Some(Histogram::LineLength),
+            "word-length" => Some(Histogram::WordLength),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Histogram::LineLength => "line length",
+            Histogram::WordLength => "word length",
+        }
+    }
+}
+
+/// The lengths counted by `--histogram`, printed in buckets.
+#[derive(Debug, Default)]
+struct HistogramReport {
+    lengths: Lengths,
+}
+
+impl HistogramReport {
+    /// The widest a bar gets, for the fullest bucket.
+    const BAR: u64 = 40;
+
+    fn parse(input: &str, kind: Histogram, args: &Args) -> Self {
+        let mut report = HistogramReport::default();
+        let items = match kind {
+            Histogram::LineLength => args.lines_of(input),
+            Histogram::WordLength => args.words_of(input),
+        };
+        for item in items {
+            report.lengths.record(item.chars().count());
+        }
+        report
+    }
+
+    fn add(&mut self, other: &HistogramReport) {
+        self.lengths.add(&other.lengths);
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str, args: &Args) -> io::Result<()> {
+        let Some(kind) = args.histogram else {
+            return Ok(());
+        };
+        let mut buckets = Vec::new();
+        let mut low = 0;
+        for &high in &args.buckets {
+            let count = self.lengths.counts.range(low..=high).map(|(_, n)| n).sum();
+            buckets.push((format!("{}-{}", low, high), count));
+            low = high + 1;
+        }
+        let count = self.lengths.counts.range(low..).map(|(_, n)| n).sum();
+        buckets.push((format!(">{}", low - 1), count));
+
+        let label_width = buckets
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        let fullest = buckets.iter().map(|&(_, count)| count).max().unwrap_or(0);
+        let count_width = fullest.to_string().len();
+        for (label, count) in buckets {
+            // Any line at all gets at least a one-character bar
+            let bar = match count {
+                0 => 0,
+                _ => (count * HistogramReport::BAR).div_ceil(fullest),
+            };
+            let row = format!(
+                "{}: {} {:<label_width$} {:>count_width$} {}",
+                filename,
+                kind.name(),
+                label,
+                count,
+                "#".repeat(bar as usize)
+            );
+            writeln!(out, "{}", row.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// The lengths of the lines and words of an input, for `--stats`.
+#[derive(Debug, Default)]
+struct StatsReport {
+    lines: Lengths,
+    words: Lengths,
+}
+
+impl StatsReport {
+    fn parse(input: &str, args: &Args) -> Self {
+        let mut report = StatsReport::default();
+        for line in args.lines_of(input) {
+            report.lines.record(line.chars().count());
+        }
+        for word in args.words_of(input) {
+            report.words.record(word.chars().count());
+        }
+        report
+    }
+
+    fn add(&mut self, other: &StatsReport) {
+        self.lines.add(&other.lines);
+        self.words.add(&other.words);
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str, args: &Args) -> io::Result<()> {
+        let lines = self.lines.summary(args.decimal_sep);
+        let words = self.words.summary(args.decimal_sep);
+        writeln!(out, "{}: line length {}", filename, lines)?;
+        writeln!(out, "{}: word length {}", filename, words)
+    }
+}
+
+/// Sentences, words and syllables, for `--readability`. Only words with a
+/// letter in them are counted.
+#[derive(Debug, Default)]
+struct ReadabilityReport {
+    sentences: u64,
+    words: u64,
+    syllables: u64,
+}
+
+impl ReadabilityReport {
+    fn parse(input: &str, args: &Args) -> Self {
+        let mut report = ReadabilityReport {
+            sentences: args.sentences_in(input),
+            ..Default::default()
+        };
+        for word in args.words_of(input) {
+            if word.chars().any(char::is_alphabetic) {
+                report.words += 1;
+                report.syllables += syllables(word);
+            }
+        }
+        report
+    }
+
+    fn add(&mut self, other: &ReadabilityReport) {
+        self.sentences += other.sentences;
+        self.words += other.words;
+        self.syllables += other.syllables;
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str, args: &Args) -> io::Result<()> {
+        if self.words == 0 {
+            return writeln!(out, "{}: readability none", filename);
+        }
+        // Text that never ends a sentence is still one
+        let words_per_sentence = self.words as f64 / self.sentences.max(1) as f64;
+        let syllables_per_word = self.syllables as f64 / self.words as f64;
+        let grade = 0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59;
+        let ease = 206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word;
+        writeln!(
+            out,
+            "{}: readability grade {} ease {}",
+            filename,
+            decimal(grade, 1, args.decimal_sep),
+            decimal(ease, 1, args.decimal_sep)
+        )
+    }
+}
+
+/// Estimates the syllables of an English word as its groups of vowels, less a
+/// silent final e, and at least one.
+fn syllables(word: &str) -> u64 {
+    let letters: Vec<char> = word
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let is_vowel = |c: &char| "aeiouy".contains(*c);
+
+    let mut groups = 0;
+    let mut in_group = false;
+    for c in &letters {
+        let vowel = is_vowel(c);
+        if vowel && !in_group {
+            groups += 1;
+        }
+        in_group = vowel;
+    }
+    // The e of "make" is silent, but not those of "table" or "free"
+    if let [.., before, last, 'e'] = letters[..] {
+        let sounded = is_vowel(&last) || last == 'l' && !is_vowel(&before);
+        if !sounded && groups > 1 {
+            groups -= 1;
+        }
+    }
+    groups.max(1)
+}
+
+/// Number of characters within each requested code point range.
+#[derive(Debug, Default)]
+struct CharRangeReport {
+    ranges: Vec<(char, char)>,
+    counts: Vec<u64>,
+}
+
+impl CharRangeReport {
+    fn parse(input: &str, ranges: &[(char, char)]) -> Self {
+        let mut counts = vec![0; ranges.len()];
+        for c in input.chars() {
+            for (i, (start, end)) in ranges.iter().enumerate() {
+                if (start..=end).contains(&&c) {
+                    counts[i] += 1;
+                }
+            }
+        }
+        CharRangeReport {
+            ranges: ranges.to_vec(),
+            counts,
+        }
+    }
+
+    fn add(&mut self, other: &CharRangeReport) {
+        if self.ranges.is_empty() {
+            self.ranges = other.ranges.clone();
+            self.counts = vec![0; other.ranges.len()];
+        }
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str) -> io::Result<()> {
+        for ((start, end), count) in self.ranges.iter().zip(&self.counts) {
+            writeln!(
+                out,
+                "{}: chars U+{:04X}-U+{:04X} {}",
+                filename, *start as u32, *end as u32, count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of words starting with each (lowercased) character.
+#[derive(Debug, Default)]
+struct FirstCharReport {
+    initials: HashMap<char, u64>,
+}
+
+impl FirstCharReport {
+    fn parse(input: &str) -> Self {
+        let mut report = FirstCharReport::default();
+        for word in input.split_whitespace() {
+            let first = word.chars().next().unwrap();
+            let folded = first.to_lowercase().next().unwrap_or(first);
+            *report.initials.entry(folded).or_default() += 1;
+        }
+        report
+    }
+
+    fn add(&mut self, other: &FirstCharReport) {
+        for (&initial, count) in &other.initials {
+            *self.initials.entry(initial).or_default() += count;
+        }
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str) -> io::Result<()> {
+        let mut initials: Vec<_> = self.initials.iter().collect();
+        initials.sort();
+        let initials = initials
+            .iter()
+            .map(|(initial, count)| format!("{} {}", initial, count))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(out, "{}: first characters {}", filename, initials)
+    }
+}
+
+/// Occurrences of each distinct line, for finding repeated lines.
+#[derive(Debug, Default)]
+struct RepeatReport {
+    lines: HashMap<String, u64>,
+    /// Maximum number of distinct lines to track, if capped
+    cap: Option<usize>,
+    /// Whether lines were left untracked because the cap was reached
+    capped: bool,
+}
+
+impl RepeatReport {
+    fn new(cap: Option<usize>) -> Self {
+        RepeatReport {
+            cap,
+            ..Default::default()
+        }
+    }
+
+    fn record(&mut self, line: &str, count: u64) {
+        if let Some(seen) = self.lines.get_mut(line) {
+            *seen += count;
+        } else if self.cap.is_some_and(|cap| self.lines.len() >= cap) {
+            self.capped = true;
+        } else {
+            self.lines.insert(line.to_string(), count);
+        }
+    }
+
+    fn parse(input: &str, cap: Option<usize>) -> Self {
+        let mut report = RepeatReport::new(cap);
+        input.lines().for_each(|line| report.record(line, 1));
+        report
+    }
+
+    fn add(&mut self, other: &RepeatReport) {
+        self.cap = other.cap;
+        self.capped |= other.capped;
+        for (line, &count) in &other.lines {
+            self.record(line, count);
+        }
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str) -> io::Result<()> {
+        let repeated = self.lines.values().filter(|&&count| count > 1).count();
+        // Break ties on the line itself so the output doesn't depend on hash order
+        let top = self
+            .lines
+            .iter()
+            .filter(|(_, &count)| count > 1)
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)));
+        let capped = if self.capped { " (capped)" } else { "" };
+        match top {
+            Some((line, count)) => writeln!(
+                out,
+                "{}: repeated lines {}{}, most repeated {}x {:?}",
+                filename, repeated, capped, count, line
+            )?,
+            None => writeln!(out, "{}: repeated lines 0{}", filename, capped)?,
+        }
+        Ok(())
+    }
+}
+
+/// The distinct lines of an input, for `--unique-lines`: the lines
+/// themselves, or only a sketch of them for `--approximate`.
+#[derive(Debug, Clone)]
+enum LineSet {
+    Exact(HashSet<String>),
+    Approximate(Sketch),
+}
+
+impl Default for LineSet {
+    fn default() -> Self {
+        LineSet::Exact(HashSet::new())
+    }
+}
+
+impl LineSet {
+    fn new(approximate: bool) -> Self {
+        if approximate {
+            LineSet::Approximate(Sketch::new())
+        } else {
+            LineSet::default()
+        }
+    }
+
+    fn insert(&mut self, line: &str) {
+        match self {
+            LineSet::Exact(lines) if !lines.contains(line) => {
+                lines.insert(line.to_string());
+            }
+            LineSet::Exact(_) => {}
+            LineSet::Approximate(sketch) => sketch.insert(line),
+        }
+    }
+
+    fn len(&self) -> u64 {
+        match self {
+            LineSet::Exact(lines) => lines.len() as u64,
+            LineSet::Approximate(sketch) => sketch.estimate(),
+        }
+    }
+
+    fn add(&mut self, other: &LineSet) {
+        match (&mut *self, other) {
+            (LineSet::Exact(lines), LineSet::Exact(other)) => lines.extend(other.iter().cloned()),
+            (LineSet::Approximate(sketch), LineSet::Approximate(other)) => sketch.merge(other),
+            // The default empty set stands in for a sketch until the first one
+            (this, other) => *this = other.clone(),
+        }
+    }
+}
+
+/// A HyperLogLog sketch, estimating how many distinct values were inserted
+/// from the longest run of leading zeros among their hashes.
+#[derive(Debug, Clone)]
+struct Sketch {
+    registers: Vec<u8>,
+}
+
+impl Sketch {
+    /// The hash bits picking a register, for 2^14 registers and a standard
+    /// error of 1.04 / sqrt(2^14), about 0.8%
+    const BITS: u32 = 14;
+
+    fn new() -> Self {
+        Sketch {
+            registers: vec![0; 1 << Sketch::BITS],
+        }
+    }
+
+    fn insert(&mut self, value: &str) {
+        // DefaultHasher::new always uses the same keys, so sketches of
+        // different inputs can be merged
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let register = (hash >> (64 - Sketch::BITS)) as usize;
+        // A marker bit stops the count at the end of the remaining bits
+        let rank = ((hash << Sketch::BITS) | 1 << (Sketch::BITS - 1)).leading_zeros() + 1;
+        self.registers[register] = self.registers[register].max(rank as u8);
+    }
+
+    fn merge(&mut self, other: &Sketch) {
+        for (register, &other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(other);
+        }
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&rank| 2f64.powi(-(rank as i32)))
+            .sum();
+        let estimate = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+        // Small counts are estimated better from the registers still empty
+        let empty = self.registers.iter().filter(|&&rank| rank == 0).count();
+        if estimate <= 2.5 * m && empty > 0 {
+            (m * (m / empty as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+/// A BPE tokenizer for `--tokens`, as LLM APIs count their input.
+#[cfg(feature = "tokens")]
+#[derive(Clone, Copy)]
+struct Tokenizer(&'static tiktoken_rs::CoreBPE);
+
+/// Without the tokens feature there are no tokenizers to load.
+#[cfg(not(feature = "tokens"))]
+#[derive(Debug, Clone, Copy)]
+enum Tokenizer {}
+
+#[cfg(feature = "tokens")]
+impl std::fmt::Debug for Tokenizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("Tokenizer")
+    }
+}
+
+#[cfg(feature = "tokens")]
+impl Tokenizer {
+    /// Loads a built-in encoding by name, or otherwise reads a vocabulary
+    /// in the tiktoken format: a base64 token and its rank on each line.
+    /// Such vocabularies split text as o200k_base does.
+    fn load(encoding: &str) -> Result<Self, String> {
+        use base64::Engine;
+
+        match encoding {
+            "o200k_base" | "o200k" => return Ok(Tokenizer(tiktoken_rs::o200k_base_singleton())),
+            "cl100k_base" | "cl100k" => return Ok(Tokenizer(tiktoken_rs::cl100k_base_singleton())),
+            _ => {}
+        }
+        let vocabulary =
+            std::fs::read_to_string(encoding).map_err(|e| io_error(encoding, &e).to_string())?;
+        let mut encoder = HashMap::new();
+        let mut ranks = HashSet::new();
+        for (i, line) in vocabulary.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parsed = line.split_once(' ').and_then(|(token, rank)| {
+                let token = base64::engine::general_purpose::STANDARD
+                    .decode(token)
+                    .ok()?;
+                Some((token, rank.trim().parse().ok()?))
+            });
+            // Both tokens and ranks have to be unique for decoding
+            match parsed {
+                Some((token, rank)) if ranks.insert(rank) && !encoder.contains_key(&token) => {
+                    encoder.insert(token, rank);
+                }
+                _ => {
+                    return Err(format!(
+                        "wc: {}:{}: invalid vocabulary line",
+                        encoding,
+                        i + 1
+                    ))
+                }
+            }
+        }
+        // Every byte needs a token of its own, or some text can't be encoded
+        if let Some(byte) = (0..=u8::MAX).find(|byte| !encoder.contains_key(&vec![*byte])) {
+            return Err(format!(
+                "wc: {}: no token for byte {:#04x} in vocabulary",
+                encoding, byte
+            ));
+        }
+        let bpe = tiktoken_rs::CoreBPE::new(
+            encoder.into_iter().collect(),
+            Default::default(),
+            tiktoken_rs::O200K_BASE_PAT_STR,
+        )
+        .map_err(|e| format!("wc: {}: {}", encoding, e))?;
+        Ok(Tokenizer(Box::leak(Box::new(bpe))))
+    }
+
+    /// The tokens in `text`, where special tokens such as `<|endoftext|>`
+    /// are taken as plain text.
+    fn count(self, text: &str) -> u64 {
+        self.0.encode_ordinary(text).len() as u64
+    }
+}
+
+#[cfg(not(feature = "tokens"))]
+impl Tokenizer {
+    fn load(_: &str) -> Result<Self, String> {
+        Err(String::from(
+            "wc: tokens can only be counted when built with the tokens feature",
+        ))
+    }
+
+    fn count(self, _: &str) -> u64 {
+        match self {}
+    }
+}
+
+/// `word` lowercased for `--ignore-case`, or as it is.
+fn fold_word(word: &str, ignore_case: bool) -> Cow<'_, str> {
+    if ignore_case {
+        Cow::Owned(word.to_lowercase())
+    } else {
+        Cow::Borrowed(word)
+    }
+}
+
+/// Occurrences of each distinct word, for `--frequency`.
+#[derive(Debug, Default, Clone)]
+struct FrequencyReport {
+    words: HashMap<String, u64>,
+    /// Whether words are lowercased before they are counted
+    ignore_case: bool,
+}
+
+impl FrequencyReport {
+    fn new(ignore_case: bool) -> Self {
+        FrequencyReport {
+            ignore_case,
+            ..Default::default()
+        }
+    }
+
+    fn record(&mut self, word: &str) {
+        let word = fold_word(word, self.ignore_case);
+        match self.words.get_mut(word.as_ref()) {
+            Some(count) => *count += 1,
+            None => {
+                self.words.insert(word.into_owned(), 1);
+            }
+        }
+    }
+
+    fn parse(input: &str, args: &Args) -> Self {
+        let mut report = FrequencyReport::new(args.ignore_case);
+        if args.unicode_words {
+            input.unicode_words().for_each(|word| report.record(word));
+        } else {
+            input
+                .split_whitespace()
+                .for_each(|word| report.record(word));
+        }
+        report
+    }
+
+    fn add(&mut self, other: &FrequencyReport) {
+        for (word, count) in &other.words {
+            *self.words.entry(word.clone()).or_default() += count;
+        }
+    }
+
+    fn print(&self, out: &mut impl Write, filename: &str, top: usize) -> io::Result<()> {
+        let mut words: Vec<_> = self.words.iter().collect();
+        // Break ties on the word itself so the output doesn't depend on hash order
+        words.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        for (word, count) in words.into_iter().take(top) {
+            writeln!(out, "{}: frequency {} {}", filename, count, word)?;
+        }
+        Ok(())
+    }
+}
+
+/// Text pulled out of markup, where the elements that were removed still
+/// separate words. A separator is only written between two words that
+/// would otherwise run together, and is counted so that it can be left out
+/// of the characters.
+#[derive(Debug, Default)]
+struct Prose {
+    text: String,
+    separators: u64,
+    /// Whether the next word starts a new element
+    split: bool,
+}
+
+impl Prose {
+    fn with_capacity(capacity: usize) -> Self {
+        Prose {
+            text: String::with_capacity(capacity),
+            ..Prose::default()
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        if std::mem::take(&mut self.split) && !s.starts_with(char::is_whitespace) {
+            self.text.push('\n');
+            self.separators += 1;
+        }
+        self.text.push_str(s);
+    }
+
+    /// Ends an element, so the word before can't run into the one after.
+    fn split(&mut self) {
+        self.split |= self.text.ends_with(|c: char| !c.is_whitespace());
+    }
+}
+
+/// Extracts the text nodes of a Markdown document, dropping headings markers,
+/// emphasis, link targets and other syntax.
+fn markdown_text(input: &str, exclude_code: bool) -> Prose {
+    let mut text = Prose::with_capacity(input.len());
+    let mut in_code_block = false;
+
+    for event in Parser::new(input) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                text.split();
+            }
+            Event::Text(t) if !(in_code_block && exclude_code) => text.push_str(&t),
+            Event::Code(t) => text.push_str(&t),
+            // A line break in the source is white space already
+            Event::SoftBreak | Event::HardBreak => text.push_str("\n"),
+            // Inline markup can sit in the middle of a word
+            Event::End(
+                TagEnd::Emphasis
+                | TagEnd::Strong
+                | TagEnd::Strikethrough
+                | TagEnd::Link
+                | TagEnd::Image,
+            ) => {}
+            // Separate block-level elements so their words don't run together
+            Event::End(_) => text.split(),
+            _ => {}
+        }
+    }
+    text
+}
+
+// Tags whose contents are never rendered as text
+const HTML_HIDDEN_TAGS: [&str; 2] = ["script", "style"];
+
+// Tags that never split a word when they are removed
+const HTML_INLINE_TAGS: [&str; 14] = [
+    "a", "abbr", "b", "code", "em", "font", "i", "mark", "s", "small", "span", "strong", "sub",
+    "sup",
+];
+
+/// Extracts the visible text of an HTML document by removing tags, comments and
+/// `<script>`/`<style>` elements, and decoding character entities.
+fn html_text(input: &str) -> Prose {
+    let mut text = Prose::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find(['<', '&']) {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if rest.starts_with('&') {
+            let (decoded, len) = decode_entity(rest);
+            text.push_str(&decoded);
+            rest = &rest[len..];
+            continue;
+        }
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+
+        let Some(end) = rest.find('>') else {
+            // A stray '<' that never closes is just text
+            text.push_str("<");
+            rest = &rest[1..];
+            continue;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+
+        if !tag.starts_with('/') && HTML_HIDDEN_TAGS.contains(&name.as_str()) {
+            rest = match closing_tag(rest, &name) {
+                Some(close) => rest[close..]
+                    .find('>')
+                    .map_or("", |end| &rest[close + end + 1..]),
+                None => "",
+            };
+        } else if !HTML_INLINE_TAGS.contains(&name.as_str()) {
+            text.split();
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// Where the first closing tag of the element `name` is in `input`, in any
+/// case.
+fn closing_tag(input: &str, name: &str) -> Option<usize> {
+    let mut from = 0;
+    while let Some(found) = input[from..].find("</") {
+        let start = from + found + 2;
+        let rest = &input.as_bytes()[start..];
+        if rest.len() >= name.len() && rest[..name.len()].eq_ignore_ascii_case(name.as_bytes()) {
+            return Some(start - 2);
+        }
+        from = start;
+    }
+    None
+}
+
+// The named character entities of HTML 4, and &apos;, in name order
+const HTML_ENTITIES: [(&str, char); 253] = [
+    ("AElig", '\u{c6}'),
+    ("Aacute", '\u{c1}'),
+    ("Acirc", '\u{c2}'),
+    ("Agrave", '\u{c0}'),
+    ("Alpha", '\u{391}'),
+    ("Aring", '\u{c5}'),
+    ("Atilde", '\u{c3}'),
+    ("Auml", '\u{c4}'),
+    ("Beta", '\u{392}'),
+    ("Ccedil", '\u{c7}'),
+    ("Chi", '\u{3a7}'),
+    ("Dagger", '\u{2021}'),
+    ("Delta", '\u{394}'),
+    ("ETH", '\u{d0}'),
+    ("Eacute", '\u{c9}'),
+    ("Ecirc", '\u{ca}'),
+    ("Egrave", '\u{c8}'),
+    ("Epsilon", '\u{395}'),
+    ("Eta", '\u{397}'),
+    ("Euml", '\u{cb}'),
+    ("Gamma", '\u{393}'),
+    ("Iacute", '\u{cd}'),
+    ("Icirc", '\u{ce}'),
+    ("Igrave", '\u{cc}'),
+    ("Iota", '\u{399}'),
+    ("Iuml", '\u{cf}'),
+    ("Kappa", '\u{39a}'),
+    ("Lambda", '\u{39b}'),
+    ("Mu", '\u{39c}'),
+    ("Ntilde", '\u{d1}'),
+    ("Nu", '\u{39d}'),
+    ("OElig", '\u{152}'),
+    ("Oacute", '\u{d3}'),
+    ("Ocirc", '\u{d4}'),
+    ("Ograve", '\u{d2}'),
+    ("Omega", '\u{3a9}'),
+    ("Omicron", '\u{39f}'),
+    ("Oslash", '\u{d8}'),
+    ("Otilde", '\u{d5}'),
+    ("Ouml", '\u{d6}'),
+    ("Phi", '\u{3a6}'),
+    ("Pi", '\u{3a0}'),
+    ("Prime", '\u{2033}'),
+    ("Psi", '\u{3a8}'),
+    ("Rho", '\u{3a1}'),
+    ("Scaron", '\u{160}'),
+    ("Sigma", '\u{3a3}'),
+    ("THORN", '\u{de}'),
+    ("Tau", '\u{3a4}'),
+    ("Theta", '\u{398}'),
+    ("Uacute", '\u{da}'),
+    ("Ucirc", '\u{db}'),
+    ("Ugrave", '\u{d9}'),
+    ("Upsilon", '\u{3a5}'),
+    ("Uuml", '\u{dc}'),
+    ("Xi", '\u{39e}'),
+    ("Yacute", '\u{dd}'),
+    ("Yuml", '\u{178}'),
+    ("Zeta", '\u{396}'),
+    ("aacute", '\u{e1}'),
+    ("acirc", '\u{e2}'),
+    ("acute", '\u{b4}'),
+    ("aelig", '\u{e6}'),
+    ("agrave", '\u{e0}'),
+    ("alefsym", '\u{2135}'),
+    ("alpha", '\u{3b1}'),
+    ("amp", '&'),
+    ("and", '\u{2227}'),
+    ("ang", '\u{2220}'),
+    ("apos", '\''),
+    ("aring", '\u{e5}'),
+    ("asymp", '\u{2248}'),
+    ("atilde", '\u{e3}'),
+    ("auml", '\u{e4}'),
+    ("bdquo", '\u{201e}'),
+    ("beta", '\u{3b2}'),
+    ("brvbar", '\u{a6}'),
+    ("bull", '\u{2022}'),
+    ("cap", '\u{2229}'),
+    ("ccedil", '\u{e7}'),
+    ("cedil", '\u{b8}'),
+    ("cent", '\u{a2}'),
+    ("chi", '\u{3c7}'),
+    ("circ", '\u{2c6}'),
+    ("clubs", '\u{2663}'),
+    ("cong", '\u{2245}'),
+    ("copy", '\u{a9}'),
+    ("crarr", '\u{21b5}'),
+    ("cup", '\u{222a}'),
+    ("curren", '\u{a4}'),
+    ("dArr", '\u{21d3}'),
+    ("dagger", '\u{2020}'),
+    ("darr", '\u{2193}'),
+    ("deg", '\u{b0}'),
+    ("delta", '\u{3b4}'),
+    ("diams", '\u{2666}'),
+    ("divide", '\u{f7}'),
+    ("eacute", '\u{e9}'),
+    ("ecirc", '\u{ea}'),
+    ("egrave", '\u{e8}'),
+    ("empty", '\u{2205}'),
+    ("emsp", '\u{2003}'),
+    ("ensp", '\u{2002}'),
+    ("epsilon", '\u{3b5}'),
+    ("equiv", '\u{2261}'),
+    ("eta", '\u{3b7}'),
+    ("eth", '\u{f0}'),
+    ("euml", '\u{eb}'),
+    ("euro", '\u{20ac}'),
+    ("exist", '\u{2203}'),
+    ("fnof", '\u{192}'),
+    ("forall", '\u{2200}'),
+    ("frac12", '\u{bd}'),
+    ("frac14", '\u{bc}'),
+    ("frac34", '\u{be}'),
+    ("frasl", '\u{2044}'),
+    ("gamma", '\u{3b3}'),
+    ("ge", '\u{2265}'),
+    ("gt", '>'),
+    ("hArr", '\u{21d4}'),
+    ("harr", '\u{2194}'),
+    ("hearts", '\u{2665}'),
+    ("hellip", '\u{2026}'),
+    ("iacute", '\u{ed}'),
+    ("icirc", '\u{ee}'),
+    ("iexcl", '\u{a1}'),
+    ("igrave", '\u{ec}'),
+    ("image", '\u{2111}'),
+    ("infin", '\u{221e}'),
+    ("int", '\u{222b}'),
+    ("iota", '\u{3b9}'),
+    ("iquest", '\u{bf}'),
+    ("isin", '\u{2208}'),
+    ("iuml", '\u{ef}'),
+    ("kappa", '\u{3ba}'),
+    ("lArr", '\u{21d0}'),
+    ("lambda", '\u{3bb}'),
+    ("lang", '\u{2329}'),
+    ("laquo", '\u{ab}'),
+    ("larr", '\u{2190}'),
+    ("lceil", '\u{2308}'),
+    ("ldquo", '\u{201c}'),
+    ("le", '\u{2264}'),
+    ("lfloor", '\u{230a}'),
+    ("lowast", '\u{2217}'),
+    ("loz", '\u{25ca}'),
+    ("lrm", '\u{200e}'),
+    ("lsaquo", '\u{2039}'),
+    ("lsquo", '\u{2018}'),
+    ("lt", '<'),
+    ("macr", '\u{af}'),
+    ("mdash", '\u{2014}'),
+    ("micro", '\u{b5}'),
+    ("middot", '\u{b7}'),
+    ("minus", '\u{2212}'),
+    ("mu", '\u{3bc}'),
+    ("nabla", '\u{2207}'),
+    ("nbsp", '\u{a0}'),
+    ("ndash", '\u{2013}'),
+    ("ne", '\u{2260}'),
+    ("ni", '\u{220b}'),
+    ("not", '\u{ac}'),
+    ("notin", '\u{2209}'),
+    ("nsub", '\u{2284}'),
+    ("ntilde", '\u{f1}'),
+    ("nu", '\u{3bd}'),
+    ("oacute", '\u{f3}'),
+    ("ocirc", '\u{f4}'),
+    ("oelig", '\u{153}'),
+    ("ograve", '\u{f2}'),
+    ("oline", '\u{203e}'),
+    ("omega", '\u{3c9}'),
+    ("omicron", '\u{3bf}'),
+    ("oplus", '\u{2295}'),
+    ("or", '\u{2228}'),
+    ("ordf", '\u{aa}'),
+    ("ordm", '\u{ba}'),
+    ("oslash", '\u{f8}'),
+    ("otilde", '\u{f5}'),
+    ("otimes", '\u{2297}'),
+    ("ouml", '\u{f6}'),
+    ("para", '\u{b6}'),
+    ("part", '\u{2202}'),
+    ("permil", '\u{2030}'),
+    ("perp", '\u{22a5}'),
+    ("phi", '\u{3c6}'),
+    ("pi", '\u{3c0}'),
+    ("piv", '\u{3d6}'),
+    ("plusmn", '\u{b1}'),
+    ("pound", '\u{a3}'),
+    ("prime", '\u{2032}'),
+    ("prod", '\u{220f}'),
+    ("prop", '\u{221d}'),
+    ("psi", '\u{3c8}'),
+    ("quot", '"'),
+    ("rArr", '\u{21d2}'),
+    ("radic", '\u{221a}'),
+    ("rang", '\u{232a}'),
+    ("raquo", '\u{bb}'),
+    ("rarr", '\u{2192}'),
+    ("rceil", '\u{2309}'),
+    ("rdquo", '\u{201d}'),
+    ("real", '\u{211c}'),
+    ("reg", '\u{ae}'),
+    ("rfloor", '\u{230b}'),
+    ("rho", '\u{3c1}'),
+    ("rlm", '\u{200f}'),
+    ("rsaquo", '\u{203a}'),
+    ("rsquo", '\u{2019}'),
+    ("sbquo", '\u{201a}'),
+    ("scaron", '\u{161}'),
+    ("sdot", '\u{22c5}'),
+    ("sect", '\u{a7}'),
+    ("shy", '\u{ad}'),
+    ("sigma", '\u{3c3}'),
+    ("sigmaf", '\u{3c2}'),
+    ("sim", '\u{223c}'),
+    ("spades", '\u{2660}'),
+    ("sub", '\u{2282}'),
+    ("sube", '\u{2286}'),
+    ("sum", '\u{2211}'),
+    ("sup", '\u{2283}'),
+    ("sup1", '\u{b9}'),
+    ("sup2", '\u{b2}'),
+    ("sup3", '\u{b3}'),
+    ("supe", '\u{2287}'),
+    ("szlig", '\u{df}'),
+    ("tau", '\u{3c4}'),
+    ("there4", '\u{2234}'),
+    ("theta", '\u{3b8}'),
+    ("thetasym", '\u{3d1}'),
+    ("thinsp", '\u{2009}'),
+    ("thorn", '\u{fe}'),
+    ("tilde", '\u{2dc}'),
+    ("times", '\u{d7}'),
+    ("trade", '\u{2122}'),
+    ("uArr", '\u{21d1}'),
+    ("uacute", '\u{fa}'),
+    ("uarr", '\u{2191}'),
+    ("ucirc", '\u{fb}'),
+    ("ugrave", '\u{f9}'),
+    ("uml", '\u{a8}'),
+    ("upsih", '\u{3d2}'),
+    ("upsilon", '\u{3c5}'),
+    ("uuml", '\u{fc}'),
+    ("weierp", '\u{2118}'),
+    ("xi", '\u{3be}'),
+    ("yacute", '\u{fd}'),
+    ("yen", '\u{a5}'),
+    ("yuml", '\u{ff}'),
+    ("zeta", '\u{3b6}'),
+    ("zwj", '\u{200d}'),
+    ("zwnj", '\u{200c}'),
+];
+
+/// Decodes the character entity at the start of `input`, returning the decoded
+/// text and the number of bytes consumed. Unknown entities are kept verbatim.
+fn decode_entity(input: &str) -> (Cow<'_, str>, usize) {
+    let literal = (Cow::Borrowed("&"), 1);
+    // Entity names are short, so don't scan the whole input for a ';'
+    let Some((end, _)) = input.char_indices().take(32).find(|&(_, c)| c == ';') else {
+        return literal;
+    };
+    let entity = &input[1..end];
+
+    let named = HTML_ENTITIES
+        .binary_search_by_key(&entity, |&(name, _)| name)
+        .ok()
+        .map(|i| HTML_ENTITIES[i].1);
+    let decoded = named.or_else(|| {
+        entity
+            .strip_prefix("#x")
+            .or_else(|| entity.strip_prefix("#X"))
+            .map(|hex| u32::from_str_radix(hex, 16))
+            .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+            .and_then(|code| code.ok())
+            .and_then(char::from_u32)
+    });
+
+    match decoded {
+        Some(c) => (Cow::Owned(c.to_string()), end + 1),
+        None => literal,
+    }
+}
+
+// Environments holding math, code or drawings rather than prose
+const LATEX_HIDDEN_ENVIRONMENTS: [&str; 16] = [
+    "align",
+    "align*",
+    "comment",
+    "displaymath",
+    "equation",
+    "equation*",
+    "gather",
+    "gather*",
+    "lstlisting",
+    "math",
+    "minted",
+    "multline",
+    "multline*",
+    "tikzpicture",
+    "verbatim",
+    "verbatim*",
+];
+
+// Commands whose arguments are references, paths or definitions rather than prose
+const LATEX_HIDDEN_COMMANDS: [&str; 20] = [
+    "bibliography",
+    "bibliographystyle",
+    "cite",
+    "citep",
+    "citet",
+    "documentclass",
+    "eqref",
+    "hspace",
+    "include",
+    "includegraphics",
+    "input",
+    "label",
+    "newcommand",
+    "pageref",
+    "ref",
+    "renewcommand",
+    "setlength",
+    "url",
+    "usepackage",
+    "vspace",
+];
+
+/// Extracts the prose of a LaTeX document by removing the preamble, comments,
+/// math, code environments and commands. The arguments of most commands, such
+/// as `\emph{...}` and `\section{...}`, are kept as text.
+fn latex_text(input: &str) -> String {
+    // The preamble only sets the document up
+    let body = match input.find("\\begin{document}") {
+        Some(start) => {
+            let body = &input[start + "\\begin{document}".len()..];
+            body.find("\\end{document}")
+                .map_or(body, |end| &body[..end])
+        }
+        None => input,
+    };
+    let mut text = String::with_capacity(body.len());
+    let mut rest = body;
+
+    while let Some(start) = rest.find(['\\', '%', '$', '{', '}', '~']) {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        match rest.as_bytes()[0] {
+            // A comment runs to the end of the line, newline included
+            b'%' => rest = rest.find('\n').map_or("", |end| &rest[end + 1..]),
+            b'$' => {
+                let delimiter = if rest.starts_with("$$") { "$$" } else { "$" };
+                rest = skip_past(&rest[delimiter.len()..], delimiter);
+            }
+            b'~' => {
+                text.push(' ');
+                rest = &rest[1..];
+            }
+            b'\\' => rest = latex_command(&rest[1..], &mut text),
+            // Braces only group
+            _ => rest = &rest[1..],
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// Handles the command after a backslash at the start of `input`, adding any
+/// text it stands for, and returns the input after it.
+fn latex_command<'a>(input: &'a str, text: &mut String) -> &'a str {
+    let len = input
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(input.len());
+    if len == 0 {
+        // A control symbol: an escaped character, a line break or math
+        let Some(symbol) = input.chars().next() else {
+            return input;
+        };
+        let rest = &input[symbol.len_utf8()..];
+        match symbol {
+            '[' => return skip_past(rest, "\\]"),
+            '(' => return skip_past(rest, "\\)"),
+            '\\' => text.push('\n'),
+            '%' | '&' | '$' | '#' | '_' | '{' | '}' | ' ' => text.push(symbol),
+            // Accents such as \' and \" are dropped, leaving the letter
+            _ => {}
+        }
+        return rest;
+    }
+
+    let name = &input[..len];
+    let mut rest = &input[len..];
+    rest = rest.strip_prefix('*').unwrap_or(rest);
+    match name {
+        "begin" => {
+            let Some((environment, after)) = latex_group(rest, '{', '}') else {
+                return rest;
+            };
+            if LATEX_HIDDEN_ENVIRONMENTS.contains(&environment) {
+                return skip_past(after, &format!("\\end{{{}}}", environment));
+            }
+            text.push('\n');
+            after
+        }
+        "end" => {
+            text.push('\n');
+            latex_group(rest, '{', '}').map_or(rest, |(_, after)| after)
+        }
+        // \verb|...| is delimited by whatever character follows it
+        "verb" => match rest.chars().next() {
+            Some(delimiter) => skip_past(&rest[delimiter.len_utf8()..], &delimiter.to_string()),
+            None => rest,
+        },
+        _ if LATEX_HIDDEN_COMMANDS.contains(&name) => {
+            while let Some((_, after)) =
+                latex_group(rest, '[', ']').or_else(|| latex_group(rest, '{', '}'))
+            {
+                rest = after;
+            }
+            rest
+        }
+        // Options aren't prose, but the braced arguments usually are
+        _ => {
+            while let Some((_, after)) = latex_group(rest, '[', ']') {
+                rest = after;
+            }
+            rest
+        }
+    }
+}
+
+/// Splits a group that opens at the start of `input` into its contents and
+/// the input after it, minding nested groups.
+fn latex_group(input: &str, open: char, close: char) -> Option<(&str, &str)> {
+    let inner = input.strip_prefix(open)?;
+    let mut depth = 0;
+    for (i, c) in inner.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            if depth == 0 {
+                return Some((&inner[..i], &inner[i + 1..]));
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// The input after the next `delimiter`, or nothing if it never comes.
+fn skip_past<'a>(input: &'a str, delimiter: &str) -> &'a str {
+    input
+        .find(delimiter)
+        .map_or("", |end| &input[end + delimiter.len()..])
+}
+
+// Code points that are displayed as emoji by default
+const EMOJI_PRESENTATION: [(u32, u32); 36] = [
+    (0x231A, 0x231B),
+    (0x23E9, 0x23EC),
+    (0x23F0, 0x23F0),
+    (0x23F3, 0x23F3),
+    (0x25FD, 0x25FE),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267F, 0x267F),
+    (0x2693, 0x2693),
+    (0x26A1, 0x26A1),
+    (0x26AA, 0x26AB),
+    (0x26BD, 0x26BE),
+    (0x26C4, 0x26C5),
+    (0x26CE, 0x26CE),
+    (0x26D4, 0x26D4),
+    (0x26EA, 0x26EA),
+    (0x26F2, 0x26F5),
+    (0x26FA, 0x26FD),
+    (0x2705, 0x2705),
+    (0x270A, 0x270B),
+    (0x2728, 0x2728),
+    (0x274C, 0x274E),
+    (0x2753, 0x2757),
+    (0x2795, 0x2797),
+    (0x27B0, 0x27BF),
+    (0x2B1B, 0x2B1C),
+    (0x2B50, 0x2B55),
+    (0x1F004, 0x1F004),
+    (0x1F0CF, 0x1F0CF),
+    (0x1F18E, 0x1F18E),
+    (0x1F191, 0x1F19A),
+    (0x1F201, 0x1F251),
+    (0x1F300, 0x1F64F),
+    (0x1F680, 0x1F6FF),
+    (0x1F7E0, 0x1F7F0),
+    (0x1F900, 0x1FAFF),
+];
+
+// Symbols that are displayed as text unless followed by VS16 (U+FE0F)
+const EMOJI_TEXT_DEFAULT: [(u32, u32); 13] = [
+    (0x00A9, 0x00AE),
+    (0x203C, 0x2049),
+    (0x2122, 0x2139),
+    (0x2194, 0x21AA),
+    (0x2300, 0x23FF),
+    (0x24C2, 0x24C2),
+    (0x25AA, 0x25FE),
+    (0x2600, 0x27BF),
+    (0x2934, 0x2935),
+    (0x2B05, 0x2B55),
+    (0x3030, 0x303D),
+    (0x3297, 0x3299),
+    (0x1F170, 0x1F251),
+];
+
+fn in_ranges(c: char, ranges: &[(u32, u32)]) -> bool {
+    ranges
+        .iter()
+        .any(|&(start, end)| (start..=end).contains(&(c as u32)))
+}
+
+/// Whether a grapheme cluster is rendered as an emoji. Using whole clusters
+/// keeps ZWJ sequences, skin tones, keycaps and flags together.
+fn is_emoji(grapheme: &str) -> bool {
+    let Some(first) = grapheme.chars().next() else {
+        return false;
+    };
+    let regional_indicator = ('\u{1F1E6}'..='\u{1F1FF}').contains(&first);
+    regional_indicator
+        || grapheme.contains('\u{20E3}')
+        || in_ranges(first, &EMOJI_PRESENTATION)
+        || (grapheme.contains('\u{FE0F}') && in_ranges(first, &EMOJI_TEXT_DEFAULT))
+}
+
+#[derive(Debug)]
+struct WordCount {
+    filename: String,
+    bytes: u64,
+    chars: u64,
+    lines: u64,
+    words: u64,
+    max_line_length: u64,
+    emoji: u64,
+    whitespace: u64,
+    sentences: u64,
+    words_no_numbers: u64,
+    bytes_no_eol: u64,
+    blank_lines: u64,
+    invalid_utf8: u64,
+    tokens: u64,
+    /// Seconds, for `--reading-time`
+    reading_time: u64,
+    graphemes: u64,
+    paragraphs: u64,
+    /// Bitset of the byte values that occur in the input
+    byte_set: [u64; 4],
+    /// The distinct words of the input, for `--unique-words`
+    word_set: HashSet<String>,
+    /// The distinct lines of the input, for `--unique-lines`
+    line_set: LineSet,
+    /// The matches of each `-e` pattern
+    matches: Vec<u64>,
+    /// First line containing a tab, for `--fail-on-tabs`
+    first_tab: Option<usize>,
+    /// The encoding named by the input's byte order mark, if it has one
+    bom: Option<Encoding>,
+    /// Whether the input has a last line with no newline at its end
+    missing_newline: bool,
+    brackets: Option<BracketReport>,
+    code_blocks: Option<CodeBlockReport>,
+    continuations: Option<ContinuationReport>,
+    longest_line: Option<LongestLine>,
+    line_endings: Option<LineEndingReport>,
+    columns: Option<ColumnReport>,
+    todo: Option<TodoReport>,
+    urls: Option<UrlReport>,
+    xml: Option<XmlReport>,
+    json: Option<JsonReport>,
+    utf8: Option<Utf8Report>,
+    ascii: Option<AsciiReport>,
+    classes: Option<ClassReport>,
+    stats: Option<StatsReport>,
+    readability: Option<ReadabilityReport>,
+    histogram: Option<HistogramReport>,
+    char_ranges: Option<CharRangeReport>,
+    first_chars: Option<FirstCharReport>,
+    repeats: Option<RepeatReport>,
+    frequency: Option<FrequencyReport>,
+    /// The counts of each file inside an archive, for `--archive`
+    entries: Vec<WordCount>,
+}
+
+impl WordCount {
+    /// Counts `input`, the decoded text of the raw bytes in `raw`.
+    fn parse(filename: String, raw: &[u8], input: &str, args: &Args) -> Self {
+        let mut selected = Cow::Borrowed(input);
+        if let Some(syntax) = args.strip_comments {
+            selected = Cow::Owned(syntax.strip(&selected));
+        }
+        if let Some((start, end)) = &args.between {
+            selected = Cow::Owned(section_text(&selected, start, end));
+        }
+        // Byte counts are of the raw input unless part of it was left out
+        let (input, data) = match selected {
+            Cow::Borrowed(input) => (input, raw),
+            Cow::Owned(ref text) => (text.as_str(), text.as_bytes()),
+        };
+
+        // Words and characters are counted over the prose when markup is stripped
+        let mut text = Cow::Borrowed(input);
+        // What was written between elements to keep their words apart
+        let mut separators = 0;
+        if args.html {
+            let prose = html_text(&text);
+            separators += prose.separators;
+            text = Cow::Owned(prose.text);
+        }
+        if args.markdown {
+            let prose = markdown_text(&text, args.exclude_code);
+            separators += prose.separators;
+            text = Cow::Owned(prose.text);
+        }
+        if args.latex {
+            text = Cow::Owned(latex_text(&text));
+        }
+
+        // Counts are u64 so totals can't truncate on 32-bit targets
+        let bytes = if args.counts(Metric::Bytes) {
+            data.len() as u64
+        } else {
+            0
+        };
+        let chars = if args.counts(Metric::Chars) {
+            (text.chars().count() as u64).saturating_sub(separators)
+        } else {
+            0
+        };
+        let lines = if !args.counts(Metric::Lines) {
+            0
+        } else if input.is_empty() && args.empty_as_one_line {
+            1
+        } else if args.zero_terminated {
+            input.split_terminator('\0').count() as u64
+        } else {
+            input.lines().count() as u64
+        };
+        // --unicode-words splits at UAX #29 word boundaries instead of white space
+        let words = if !args.counts(Metric::Words) {
+            0
+        } else if args.unicode_words {
+            text.unicode_words().count() as u64
+        } else {
+            text.split_whitespace().count() as u64
+        };
+        let (max_line_length, widest) = if args.counts(Metric::MaxLineLength) {
+            widest_line(input, args.delimiter(), args.widths)
+        } else {
+            (0, 0)
+        };
+        let longest_line = (args.longest_line && !input.is_empty()).then(|| LongestLine {
+            filename: filename.clone(),
+            line: widest,
+            width: max_line_length,
+        });
+        let emoji = if args.counts(Metric::Emoji) {
+            text.graphemes(true).filter(|g| is_emoji(g)).count() as u64
+        } else {
+            0
+        };
+        let whitespace = if args.counts(Metric::Whitespace) {
+            (text.chars().filter(|c| c.is_whitespace()).count() as u64).saturating_sub(separators)
+        } else {
+            0
+        };
+        let sentences = if args.counts(Metric::Sentences) {
+            args.sentences_in(&text)
+        } else {
+            0
+        };
+        let words_no_numbers = if !args.counts(Metric::WordsNoNumbers) {
+            0
+        } else if args.unicode_words {
+            text.unicode_words()
+                .filter(|word| !is_numeric_word(word))
+                .count() as u64
+        } else {
+            text.split_whitespace()
+                .filter(|word| !is_numeric_word(word))
+                .count() as u64
+        };
+        let bytes_no_eol = if args.counts(Metric::BytesNoEol) {
+            data.iter().filter(|&&b| b != b'\r' && b != b'\n').count() as u64
+        } else {
+            0
+        };
+        let blank_lines = if !args.counts(Metric::BlankLines) {
+            0
+        } else if args.zero_terminated {
+            input
+                .split_terminator('\0')
+                .filter(|line| line.trim().is_empty())
+                .count() as u64
+        } else {
+            input.lines().filter(|line| line.trim().is_empty()).count() as u64
+        };
+        // Input decoded from another encoding has no UTF-8 to be malformed
+        let utf8 = args.input_encoding.is_none() && !args.decodes(data);
+        let invalid_utf8 = if args.counts(Metric::InvalidUtf8) && utf8 {
+            data.utf8_chunks()
+                .filter(|chunk| !chunk.invalid().is_empty())
+                .count() as u64
+        } else {
+            0
+        };
+        let tokens = match args.tokenizer {
+            Some(tokenizer) if args.counts(Metric::Tokens) => tokenizer.count(&text),
+            _ => 0,
+        };
+        let graphemes = if args.counts(Metric::Graphemes) {
+            text.graphemes(true).count() as u64
+        } else {
+            0
+        };
+        let paragraphs = if args.counts(Metric::Paragraphs) {
+            count_paragraphs(&text)
+        } else {
+            0
+        };
+        let mut byte_set = [0; 4];
+        if args.counts(Metric::DistinctBytes) {
+            for &b in data {
+                byte_set[b as usize / 64] |= 1 << (b % 64);
+            }
+        }
+        let mut word_set = HashSet::new();
+        if args.counts(Metric::UniqueWords) {
+            for word in args.words_of(&text) {
+                word_set.insert(fold_word(word, args.ignore_case).into_owned());
+            }
+        }
+        let mut line_set = LineSet::new(args.approximate);
+        if args.counts(Metric::UniqueLines) {
+            args.lines_of(input).for_each(|line| line_set.insert(line));
+        }
+        let matches = args
+            .regexps
+            .iter()
+            .map(|regex| regex.find_iter(input).count() as u64)
+            .collect();
+        let first_tab = if args.fail_on_tabs {
+            first_tab_line(input, args.leading_only)
+        } else {
+            None
+        };
+        let bom = match Encoding::detect(raw) {
+            (_, 0) => None,
+            (encoding, _) => Some(encoding),
+        };
+        let missing_newline = data.last().is_some_and(|&b| b as char != args.delimiter());
+        let brackets = args
+            .brackets
+            .then(|| BracketReport::parse(input, args.skip_strings));
+        let code_blocks = args.code_blocks.then(|| CodeBlockReport::parse(input));
+        let continuations = args
+            .continuation_lines
+            .then(|| ContinuationReport::parse(input));
+        let line_endings = (args.line_endings || args.fail_on_mixed_endings)
+            .then(|| LineEndingReport::parse(data));
+        let columns = (!args.columns.is_empty()).then(|| ColumnReport::parse(input, &args.columns));
+        let todo = args.todo.then(|| TodoReport::parse(input));
+        let urls = args.urls.then(|| UrlReport::parse(input));
+        let xml = args
+            .xml
+            .then(|| XmlReport::read(input.as_bytes()).expect("Reading a slice can't fail"));
+        let json = args.json_stats.then(|| JsonReport::parse(input));
+        let utf8 = args.utf8_stats.then(|| Utf8Report::parse(input));
+        let ascii = (args.ascii || args.fail_non_ascii)
+            .then(|| AsciiReport::parse(data, input, args.delimiter()));
+        let classes = args.classify.then(|| ClassReport::parse(&text));
+        let stats = args.stats.then(|| StatsReport::parse(&text, args));
+        let readability = args
+            .readability
+            .then(|| ReadabilityReport::parse(&text, args));
+        let histogram = args
+            .histogram
+            .map(|kind| HistogramReport::parse(&text, kind, args));
+        let char_ranges = (!args.char_ranges.is_empty())
+            .then(|| CharRangeReport::parse(&text, &args.char_ranges));
+        let first_chars = args.first_chars.then(|| FirstCharReport::parse(&text));
+        let repeats = args
+            .repeated_lines
+            .then(|| RepeatReport::parse(input, args.cap_repeat_memory));
+        let frequency = args.frequency.then(|| FrequencyReport::parse(&text, args));
+        WordCount {
+            filename,
+            bytes,
+            chars,
+            lines,
+            words,
+            max_line_length,
+            emoji,
+            whitespace,
+            sentences,
+            words_no_numbers,
+            bytes_no_eol,
+            blank_lines,
+            invalid_utf8,
+            tokens,
+            reading_time: args.reading_time(words),
+            graphemes,
+            paragraphs,
+            byte_set,
+            word_set,
+            line_set,
+            matches,
+            first_tab,
+            bom,
+            missing_newline,
+            brackets,
+            code_blocks,
+            continuations,
+            longest_line,
+            line_endings,
+            columns,
+            todo,
+            urls,
+            xml,
+            json,
+            utf8,
+            ascii,
+            classes,
+            stats,
+            readability,
+            histogram,
+            char_ranges,
+            first_chars,
+            repeats,
+            frequency,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Fills in a `--printf` template.
+    fn fill(&self, template: &[Piece]) -> String {
+        template
+            .iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => Cow::Borrowed(text.as_str()),
+                Piece::Count(metric) => Cow::Owned(metric.value(self).to_string()),
+                Piece::Filename => Cow::Borrowed(self.filename.as_str()),
+            })
+            .collect()
+    }
+
+    /// The filename and shown counts as a CSV or TSV record.
+    fn record(&self, format: Format, args: &Args) -> String {
+        let mut fields = vec![format.field(&self.filename).into_owned()];
+        fields.extend(args.metrics().map(|metric| metric.value(self).to_string()));
+        fields.join(format.separator())
+    }
+
+    /// The shown counts as a JSON object, for `--format=json`. The keys are
+    /// in column order, or alphabetical with `--sort-json-keys`.
+    /// The counts as a JSON object. The total is marked with a `"type"`, as its
+    /// name could be that of a file.
+    fn json(&self, is_total: bool, args: &Args) -> String {
+        let mut fields = vec![if is_total {
+            (Cow::Borrowed("type"), json_string("total"))
+        } else {
+            (Cow::Borrowed("filename"), json_string(&self.filename))
+        }];
+        fields.extend(
+            args.metrics()
+                .map(|metric| (metric.label(args), metric.value(self).to_string())),
+        );
+        if args.sort_json_keys {
+            fields.sort();
+        }
+        let fields: Vec<String> = fields
+            .iter()
+            .map(|(key, value)| format!("{}: {}", json_string(key), value))
+            .collect();
+        format!("{{{}}}", fields.join(", "))
+    }
+
+    /// Prints the counts row and any reports; with `percent_of`, also each
+    /// count as a share of that total.
+    fn print(
+        &self,
+        out: &mut impl Write,
+        offset: usize,
+        is_total: bool,
+        percent_of: Option<&WordCount>,
+        args: &Args,
+    ) -> io::Result<()> {
+        let columns: Vec<String> = args
+            .metrics()
+            .map(|metric| format!("{:>offset$}", metric.value(self), offset = offset))
+            .collect();
+
+        // The total's label isn't a file name
+        let filename = if is_total {
+            Cow::Borrowed(self.filename.as_str())
+        } else {
+            args.quoting.quote(&self.filename)
+        };
+        let end = args.terminator();
+        if is_total && (args.numeric_total || args.total == Total::Only) {
+            // Omit the label entirely so the total line stays purely numeric
+            write!(out, "{}{}", columns.join(" "), end)?;
+        } else {
+            write!(out, "{} {}{}", columns.join(" "), filename, end)?;
+        }
+
+        // The reports name standard input, which the counts row leaves blank
+        let reported = if self.filename.is_empty() {
+            Cow::Borrowed(display_name(&self.filename))
+        } else {
+            filename
+        };
+        if let Some(total) = percent_of {
+            let shares: Vec<String> = args
+                .metrics()
+                .map(|metric| {
+                    // An all-empty set of files has nothing to take a share of
+                    let share = match metric.value(total) {
+                        0 => 0.0,
+                        all => metric.value(self) as f64 * 100.0 / all as f64,
+                    };
+                    format!(
+                        "{} {}%",
+                        metric.label(args),
+                        decimal(share, 1, args.decimal_sep)
+                    )
+                })
+                .collect();
+            writeln!(out, "{}: of total {}", reported, shares.join(" "))?;
+        }
+
+        if args.check_bom && !is_total {
+            match self.bom {
+                Some(Encoding::Utf8Bom) => writeln!(out, "{}: UTF-8 BOM", reported)?,
+                Some(encoding) => writeln!(out, "{}: {} BOM", reported, encoding.name())?,
+                None => writeln!(out, "{}: no BOM", reported)?,
+            }
+        }
+        if let Some(brackets) = &self.brackets {
+            brackets.print(out, &reported, is_total)?;
+        }
+        if let Some(code_blocks) = &self.code_blocks {
+            code_blocks.print(out, &reported)?;
+        }
+        if let Some(continuations) = &self.continuations {
+            continuations.print(out, &reported)?;
+        }
+        if let Some(longest_line) = &self.longest_line {
+            longest_line.print(out, &reported, args)?;
+        }
+        if let Some(line_endings) = self.line_endings.as_ref().filter(|_| args.line_endings) {
+            line_endings.print(out, &reported)?;
+        }
+        if let Some(columns) = &self.columns {
+            columns.print(out, &reported)?;
+        }
+        if let Some(todo) = &self.todo {
+            todo.print(out, &reported, args.list)?;
+        }
+        if let Some(urls) = &self.urls {
+            urls.print(out, &reported, args.list)?;
+        }
+        if let Some(xml) = &self.xml {
+            xml.print(out, &reported, args.list)?;
+        }
+        if let Some(json) = &self.json {
+            json.print(out, &reported)?;
+        }
+        if let Some(utf8) = &self.utf8 {
+            utf8.print(out, &reported)?;
+        }
+        if let Some(ascii) = self.ascii.as_ref().filter(|_| args.ascii) {
+            ascii.print(out, &reported)?;
+        }
+        if let Some(classes) = &self.classes {
+            classes.print(out, &reported)?;
+        }
+        if let Some(stats) = &self.stats {
+            stats.print(out, &reported, args)?;
+        }
+        if let Some(readability) = &self.readability {
+            readability.print(out, &reported, args)?;
+        }
+        if let Some(histogram) = &self.histogram {
+            histogram.print(out, &reported, args)?;
+        }
+        if let Some(char_ranges) = &self.char_ranges {
+            char_ranges.print(out, &reported)?;
+        }
+        if let Some(first_chars) = &self.first_chars {
+            first_chars.print(out, &reported)?;
+        }
+        if let Some(repeats) = &self.repeats {
+            repeats.print(out, &reported)?;
+        }
+        if let Some(frequency) = &self.frequency {
+            frequency.print(out, &reported, args.top)?;
+        }
+        Ok(())
+    }
+}
+
+/// Size of the chunks a streamed input is read in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Size from which regular files are mapped rather than read by default.
+const MMAP_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// The counts of one input, as returned by [`Counter::count_reader`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counts {
+    /// Lines, including a last line that doesn't end in a newline
+    pub lines: u64,
+    /// Runs of characters other than Unicode white space
+    pub words: u64,
+    pub chars: u64,
+    pub bytes: u64,
+    /// The display width of the widest line, with tabs stopping every 8 columns
+    pub max_line_length: u64,
+    /// Unicode white space characters
+    pub whitespace: u64,
+    /// Words that aren't purely numeric, like `42` or `(1,000)`
+    pub words_no_numbers: u64,
+    /// Bytes other than `\r` and `\n`
+    pub bytes_no_eol: u64,
+    /// Lines that are empty or hold only white space
+    pub blank_lines: u64,
+    /// Malformed UTF-8 sequences; always 0 from [`Counter::new`], which
+    /// fails on them instead
+    pub invalid_utf8: u64,
+    /// Distinct byte values
+    pub distinct_bytes: u64,
+}
+
+/// The basic counts of an input fed in chunks of any size, so that it never
+/// has to be held in memory whole. Words, lines and UTF-8 sequences cut off
+/// at the end of one chunk carry over into the next.
+#[derive(Debug, Default, Clone)]
+pub struct Counter {
+    bytes: u64,
+    chars: u64,
+    newlines: u64,
+    words: u64,
+    words_no_numbers: u64,
+    whitespace: u64,
+    bytes_no_eol: u64,
+    blank_lines: u64,
+    invalid_utf8: u64,
+    /// Whether the current line holds anything other than white space
+    line_content: bool,
+    byte_set: [u64; 4],
+    max_line_length: u64,
+    /// The 1-based number of the first line as wide as `max_line_length`
+    widest_line: u64,
+    line_width: u64,
+    widths: Widths,
+    /// Only lines, words, characters and bytes are needed, so ASCII chunks
+    /// can be scanned in bulk
+    basic: bool,
+    /// Lines end with NUL rather than newline, for `--zero-terminated`
+    zero_terminated: bool,
+    /// Invalid UTF-8 is counted as U+FFFD instead of failing, as GNU wc
+    /// carries on through arbitrary bytes
+    lossy: bool,
+    /// A UTF-8 byte order mark at the start is left out of the characters
+    /// and words, though not the bytes, and a UTF-16 or UTF-32 one has the
+    /// input kept whole to be decoded
+    skip_bom: bool,
+    /// The first bytes of the input, enough to hold a byte order mark
+    head: Vec<u8>,
+    /// The start of the input, held back until it's long enough to tell
+    /// whether it begins with a byte order mark
+    held: Vec<u8>,
+    /// The input so far, when its byte order mark says it has to be decoded
+    /// before it can be counted
+    undecoded: Option<Vec<u8>>,
+    last_byte: Option<u8>,
+    /// The start of a UTF-8 sequence cut off by the end of the last chunk
+    partial: Vec<u8>,
+    in_word: bool,
+    /// Whether the current word has only held ASCII digits and punctuation,
+    /// with `.` or `,` the only punctuation between its digits
+    word_numeric: bool,
+    word_digits: bool,
+    word_after_digits: bool,
+    /// The words seen so far, for `--frequency`
+    frequency: Option<FrequencyReport>,
+    /// The distinct words seen so far, for `--unique-words`
+    word_set: Option<HashSet<String>>,
+    ignore_case: bool,
+    /// The current word, kept only for `frequency` and `word_set`
+    word: String,
+    /// The distinct lines seen so far, for `--unique-lines`
+    line_set: Option<LineSet>,
+    /// The current line, kept only for `line_set`
+    line: String,
+    /// The JSON values seen so far, for `--json-stats`
+    json: Option<JsonScanner>,
+}
+
+impl Counter {
+    /// A counter for all of [`Counts`], expecting UTF-8 input.
+    pub fn new() -> Self {
+        Counter::default()
+    }
+
+    /// Counts all of `reader`, which has to be UTF-8.
+    pub fn count_reader(reader: impl Read) -> io::Result<Counts> {
+        let mut counter = Counter::new();
+        counter.read_from(reader)?;
+        counter.finalize()
+    }
+
+    /// A counter for only what `args` needs.
+    fn for_args(args: &Args) -> Self {
+        let extra = [
+            Metric::MaxLineLength,
+            Metric::Whitespace,
+            Metric::WordsNoNumbers,
+            Metric::BytesNoEol,
+            Metric::DistinctBytes,
+            Metric::BlankLines,
+        ];
+        let unique_words = args.counts(Metric::UniqueWords);
+        let unique_lines = args.counts(Metric::UniqueLines);
+        let basic = !extra.into_iter().any(|metric| args.counts(metric))
+            && !args.frequency
+            && !unique_words
+            && !unique_lines;
+        Counter {
+            basic,
+            frequency: args
+                .frequency
+                .then(|| FrequencyReport::new(args.ignore_case)),
+            word_set: unique_words.then(HashSet::new),
+            line_set: unique_lines.then(|| LineSet::new(args.approximate)),
+            json: args.json_stats.then(JsonScanner::default),
+            ignore_case: args.ignore_case,
+            lossy: true,
+            skip_bom: args.detect_bom,
+            zero_terminated: args.zero_terminated,
+            widths: args.widths,
+            ..Default::default()
+        }
+    }
+
+    fn delimiter(&self) -> u8 {
+        if self.zero_terminated {
+            0
+        } else {
+            b'\n'
+        }
+    }
+
+    fn read_from(&mut self, mut reader: impl Read) -> io::Result<()> {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => return Ok(()),
+                Ok(n) => self.update(&chunk[..n])?,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Like `read_from`, but answers status signals with the counts of
+    /// `filename` so far, including while a read is blocked.
+    fn read_reporting(
+        &mut self,
+        mut reader: impl Read,
+        filename: &str,
+        args: &Args,
+    ) -> io::Result<()> {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            let read = reader.read(&mut chunk);
+            if status_requested() {
+                write_status(self.clone(), filename, args);
+            }
+            match read {
+                Ok(0) => return Ok(()),
+                Ok(n) => self.update(&chunk[..n])?,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Like `read_reporting`, but also pulls the input through the XML parser
+    /// for `--xml` as it's counted.
+    fn read_xml(
+        &mut self,
+        reader: impl Read,
+        filename: &str,
+        args: &Args,
+    ) -> io::Result<XmlReport> {
+        let mut tee = CountingReader {
+            inner: reader,
+            counter: self,
+            filename,
+            args,
+        };
+        let xml = XmlReport::read(BufReader::with_capacity(CHUNK_SIZE, &mut tee))?;
+        // Anything after the end of the XML, or where it broke, still counts
+        tee.counter.read_reporting(tee.inner, filename, args)?;
+        Ok(xml)
+    }
+
+    /// Counts the next chunk of the input. Chunks can be of any size, and a
+    /// line, word or UTF-8 sequence may continue from one into the next.
+    ///
+    /// Fails with `io::ErrorKind::InvalidData` on invalid UTF-8.
+    pub fn update(&mut self, chunk: &[u8]) -> io::Result<()> {
+        if let Some(undecoded) = &mut self.undecoded {
+            undecoded.extend_from_slice(chunk);
+            self.bytes += chunk.len() as u64;
+            return Ok(());
+        }
+        if self.skip_bom && self.head.is_empty() {
+            self.held.extend_from_slice(chunk);
+            return self.sniff_bom(false);
+        }
+        self.count(chunk)
+    }
+
+    fn count(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.bytes += chunk.len() as u64;
+        if !self.basic {
+            for &b in chunk {
+                self.byte_set[b as usize / 64] |= 1 << (b % 64);
+                if b != b'\r' && b != b'\n' {
+                    self.bytes_no_eol += 1;
+                }
+            }
+        }
+        let first = self.head.is_empty();
+        let head = chunk.len().min(4 - self.head.len());
+        self.head.extend_from_slice(&chunk[..head]);
+        self.last_byte = chunk.last().copied().or(self.last_byte);
+
+        let mut chunk = chunk;
+        if first && self.skip_bom {
+            chunk = chunk.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(chunk);
+        }
+        if let Some(json) = &mut self.json {
+            json.update(chunk);
+        }
+        // Complete the sequence left over from the last chunk first
+        if let Some(&lead) = self.partial.first() {
+            let width = match lead {
+                0xF0.. => 4,
+                0xE0.. => 3,
+                _ => 2,
+            };
+            let take = chunk
+                .iter()
+                .take(width - self.partial.len())
+                .take_while(|&&b| b & 0xC0 == 0x80)
+                .count();
+            self.partial.extend_from_slice(&chunk[..take]);
+            chunk = &chunk[take..];
+            if self.partial.len() < width && chunk.is_empty() {
+                return Ok(());
+            }
+            let partial = std::mem::take(&mut self.partial);
+            match std::str::from_utf8(&partial) {
+                Ok(text) => self.count_text(text),
+                Err(_) if self.lossy => self.count_invalid(),
+                Err(e) => return Err(invalid_utf8(e)),
+            }
+        }
+
+        if self.basic && chunk.is_ascii() {
+            self.count_ascii(chunk);
+            return Ok(());
+        }
+        if self.lossy {
+            self.count_lossy(chunk);
+            return Ok(());
+        }
+        match std::str::from_utf8(chunk) {
+            Ok(text) => self.count_text(text),
+            // An incomplete sequence at the very end may be finished by the next chunk
+            Err(e) if e.error_len().is_none() => {
+                let (text, partial) = chunk.split_at(e.valid_up_to());
+                self.count_text(std::str::from_utf8(text).map_err(invalid_utf8)?);
+                self.partial = partial.to_vec();
+            }
+            Err(e) => return Err(invalid_utf8(e)),
+        }
+        Ok(())
+    }
+
+    /// The fast path for the basic counts of ASCII text, giving the same
+    /// results as `count_text` without decoding characters.
+    fn count_ascii(&mut self, chunk: &[u8]) {
+        self.chars += chunk.len() as u64;
+        self.newlines += memchr::memchr_iter(self.delimiter(), chunk).count() as u64;
+        // The ASCII characters for which char::is_whitespace holds
+        let mut in_word = self.in_word;
+        for &b in chunk {
+            let space = matches!(b, b'\t'..=b'\r' | b' ');
+            self.words += (!space && !in_word) as u64;
+            in_word = !space;
+        }
+        self.in_word = in_word;
+    }
+
+    /// Counts `chunk` without requiring it to be UTF-8, taking each invalid
+    /// sequence to be a U+FFFD character.
+    fn count_lossy(&mut self, chunk: &[u8]) {
+        let mut pieces = chunk.utf8_chunks().peekable();
+        while let Some(piece) = pieces.next() {
+            let valid = piece.valid();
+            if self.basic && valid.is_ascii() {
+                self.count_ascii(valid.as_bytes());
+            } else {
+                self.count_text(valid);
+            }
+
+            let invalid = piece.invalid();
+            let incomplete = std::str::from_utf8(invalid).is_err_and(|e| e.error_len().is_none());
+            if pieces.peek().is_none() && incomplete {
+                self.partial = invalid.to_vec();
+            } else if !invalid.is_empty() {
+                self.count_invalid();
+            }
+        }
+    }
+
+    fn count_invalid(&mut self) {
+        self.invalid_utf8 += 1;
+        self.count_text("\u{FFFD}");
+    }
+
+    fn count_text(&mut self, text: &str) {
+        let delimiter = self.delimiter() as char;
+        for c in text.chars() {
+            self.chars += 1;
+            let line = self.newlines + 1;
+            self.newlines += (c == delimiter) as u64;
+            if c == delimiter {
+                self.blank_lines += !self.line_content as u64;
+                self.line_content = false;
+            } else if !c.is_whitespace() {
+                self.line_content = true;
+            }
+            // Line widths follow max_line_width
+            match c {
+                '\n' | '\r' | '\x0c' => self.end_width(line),
+                _ if c == delimiter => self.end_width(line),
+                _ => self.line_width = self.widths.advance(self.line_width, c),
+            }
+            if let Some(line_set) = &mut self.line_set {
+                if c == delimiter {
+                    // As with str::lines, a CRLF line ending is all line ending
+                    if !self.zero_terminated && self.line.ends_with('\r') {
+                        self.line.pop();
+                    }
+                    line_set.insert(&self.line);
+                    self.line.clear();
+                } else {
+                    self.line.push(c);
+                }
+            }
+
+            if c.is_whitespace() {
+                self.whitespace += 1;
+                self.end_word();
+                continue;
+            }
+            if !self.in_word {
+                self.in_word = true;
+                self.words += 1;
+                self.word_numeric = true;
+                self.word_digits = false;
+                self.word_after_digits = false;
+            }
+            // The same rule as is_numeric_word, applied a character at a time
+            if c.is_ascii_digit() {
+                self.word_numeric &= !self.word_after_digits;
+                self.word_digits = true;
+            } else if c.is_ascii_punctuation() {
+                self.word_after_digits |= self.word_digits && c != '.' && c != ',';
+            } else {
+                self.word_numeric = false;
+            }
+            if self.frequency.is_some() || self.word_set.is_some() {
+                self.word.push(c);
+            }
+        }
+    }
+
+    /// Ends a stretch of `line` whose width is counted from zero again.
+    fn end_width(&mut self, line: u64) {
+        if self.line_width > self.max_line_length {
+            self.max_line_length = self.line_width;
+            self.widest_line = line;
+        }
+        self.line_width = 0;
+    }
+
+    fn end_word(&mut self) {
+        if self.in_word && !(self.word_numeric && self.word_digits) {
+            self.words_no_numbers += 1;
+        }
+        if self.in_word {
+            if let Some(report) = &mut self.frequency {
+                report.record(&self.word);
+            }
+            if let Some(word_set) = &mut self.word_set {
+                word_set.insert(fold_word(&self.word, self.ignore_case).into_owned());
+            }
+        }
+        self.word.clear();
+        self.in_word = false;
+    }
+
+    /// Adds the counts of `next`, which counted the input straight after this
+    /// one, joining a word split between the two. Only the basic counts can
+    /// be joined.
+    fn join(&mut self, next: Counter, next_starts_in_word: bool) {
+        // The next slice starts on a character boundary, so a sequence cut off
+        // here is invalid
+        if !self.partial.is_empty() && self.lossy {
+            self.partial.clear();
+            self.count_invalid();
+        }
+        self.bytes += next.bytes;
+        self.chars += next.chars;
+        self.newlines += next.newlines;
+        self.words += next.words;
+        if self.in_word && next_starts_in_word {
+            self.words -= 1;
+        }
+        self.in_word = next.in_word;
+        self.last_byte = next.last_byte.or(self.last_byte);
+        if self.partial.is_empty() {
+            self.partial = next.partial;
+        }
+    }
+
+    /// Ends the input and returns its counts, failing with
+    /// `io::ErrorKind::InvalidData` if it stopped inside a UTF-8 sequence.
+    pub fn finalize(mut self) -> io::Result<Counts> {
+        self.end()
+    }
+
+    /// Counts the bytes held back at the start once they show whether the
+    /// input begins with a byte order mark, or keeps them to be decoded if
+    /// it's UTF-16 or UTF-32. At the `end` of the input there's no more to
+    /// wait for.
+    fn sniff_bom(&mut self, end: bool) -> io::Result<()> {
+        const BOMS: [&[u8]; 5] = [
+            &[0xFF, 0xFE, 0x00, 0x00],
+            &[0x00, 0x00, 0xFE, 0xFF],
+            &[0xEF, 0xBB, 0xBF],
+            &[0xFE, 0xFF],
+            &[0xFF, 0xFE],
+        ];
+        let held = &self.held;
+        if !end
+            && BOMS
+                .iter()
+                .any(|bom| bom.len() > held.len() && bom.starts_with(held))
+        {
+            return Ok(());
+        }
+        let held = std::mem::take(&mut self.held);
+        match Encoding::detect(&held) {
+            (Encoding::Utf8 | Encoding::Utf8Bom, _) => self.count(&held),
+            _ => {
+                self.bytes = held.len() as u64;
+                self.head = held[..4.min(held.len())].to_vec();
+                self.undecoded = Some(held);
+                Ok(())
+            }
+        }
+    }
+
+    fn end(&mut self) -> io::Result<Counts> {
+        if !self.held.is_empty() {
+            self.sniff_bom(true)?;
+        }
+        if !self.partial.is_empty() && self.lossy {
+            self.count_invalid();
+        } else if !self.partial.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream ended inside a UTF-8 sequence",
+            ));
+        }
+        self.end_word();
+        self.end_width(self.newlines + 1);
+        if let Some(line_set) = &mut self.line_set {
+            if !self.line.is_empty() {
+                line_set.insert(&self.line);
+            }
+        }
+
+        // Like str::lines, a last line without a newline still counts
+        let unterminated = self.last_byte.is_some_and(|b| b != self.delimiter());
+        Ok(Counts {
+            lines: self.newlines + unterminated as u64,
+            words: self.words,
+            chars: self.chars,
+            bytes: self.bytes,
+            max_line_length: self.max_line_length,
+            whitespace: self.whitespace,
+            words_no_numbers: self.words_no_numbers,
+            bytes_no_eol: self.bytes_no_eol,
+            blank_lines: self.blank_lines + (unterminated && !self.line_content) as u64,
+            invalid_utf8: self.invalid_utf8,
+            distinct_bytes: self.byte_set.iter().map(|w| w.count_ones() as u64).sum(),
+        })
+    }
+
+    fn finish(mut self, filename: String, args: &Args) -> io::Result<WordCount> {
+        if !self.held.is_empty() {
+            self.sniff_bom(true)?;
+        }
+        if let Some(undecoded) = self.undecoded.take() {
+            return count_bytes(filename, &undecoded, None, args);
+        }
+        let bom = match Encoding::detect(&self.head) {
+            (_, 0) => None,
+            (encoding, _) => Some(encoding),
+        };
+        let counts = self.end()?;
+        let missing_newline = self.last_byte.is_some_and(|b| b != self.delimiter());
+        let longest_line = (args.longest_line && counts.bytes > 0).then(|| LongestLine {
+            filename: filename.clone(),
+            line: self.widest_line.max(1),
+            width: counts.max_line_length,
+        });
+        let lines = if counts.bytes == 0 && args.empty_as_one_line {
+            1
+        } else {
+            counts.lines
+        };
+        Ok(WordCount {
+            filename,
+            bytes: counts.bytes,
+            chars: counts.chars,
+            lines,
+            words: counts.words,
+            max_line_length: counts.max_line_length,
+            emoji: 0,
+            whitespace: counts.whitespace,
+            sentences: 0,
+            words_no_numbers: counts.words_no_numbers,
+            bytes_no_eol: counts.bytes_no_eol,
+            blank_lines: counts.blank_lines,
+            invalid_utf8: counts.invalid_utf8,
+            tokens: 0,
+            reading_time: args.reading_time(counts.words),
+            graphemes: 0,
+            paragraphs: 0,
+            byte_set: self.byte_set,
+            word_set: self.word_set.unwrap_or_default(),
+            line_set: self.line_set.unwrap_or_default(),
+            matches: Vec::new(),
+            first_tab: None,
+            bom,
+            missing_newline,
+            brackets: None,
+            code_blocks: None,
+            continuations: None,
+            longest_line,
+            line_endings: None,
+            columns: None,
+            todo: None,
+            urls: None,
+            xml: None,
+            json: self.json.map(JsonScanner::finish),
+            utf8: None,
+            ascii: None,
+            classes: None,
+            stats: None,
+            readability: None,
+            histogram: None,
+            char_ranges: None,
+            first_chars: None,
+            repeats: None,
+            frequency: self.frequency,
+            entries: Vec::new(),
+        })
+    }
+}
+
+fn invalid_utf8(e: std::str::Utf8Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn total<'a>(counts: impl IntoIterator<Item = &'a WordCount>) -> WordCount {
+    let mut bytes = 0;
+    let mut chars = 0;
+    let mut lines = 0;
+    let mut words = 0;
+    let mut max_line_length = 0;
+    let mut emoji = 0;
+    let mut whitespace = 0;
+    let mut sentences = 0;
+    let mut words_no_numbers = 0;
+    let mut bytes_no_eol = 0;
+    let mut blank_lines = 0;
+    let mut invalid_utf8 = 0;
+    let mut tokens = 0;
+    let mut reading_time = 0;
+    let mut graphemes = 0;
+    let mut paragraphs = 0;
+    let mut byte_set = [0; 4];
+    let mut word_set = HashSet::new();
+    let mut line_set = LineSet::default();
+    let mut matches: Vec<u64> = Vec::new();
+    let mut brackets: Option<BracketReport> = None;
+    let mut code_blocks: Option<CodeBlockReport> = None;
+    let mut continuations: Option<ContinuationReport> = None;
+    let mut line_endings: Option<LineEndingReport> = None;
+    let mut longest_line: Option<LongestLine> = None;
+    let mut columns: Option<ColumnReport> = None;
+    let mut todo: Option<TodoReport> = None;
+    let mut urls: Option<UrlReport> = None;
+    let mut xml: Option<XmlReport> = None;
+    let mut json: Option<JsonReport> = None;
+    let mut utf8: Option<Utf8Report> = None;
+    let mut ascii: Option<AsciiReport> = None;
+    let mut classes: Option<ClassReport> = None;
+    let mut stats: Option<StatsReport> = None;
+    let mut readability: Option<ReadabilityReport> = None;
+    let mut histogram: Option<HistogramReport> = None;
+    let mut char_ranges: Option<CharRangeReport> = None;
+    let mut first_chars: Option<FirstCharReport> = None;
+    let mut repeats: Option<RepeatReport> = None;
+    let mut frequency: Option<FrequencyReport> = None;
+
+    counts.into_iter().for_each(|count| {
+        bytes += count.bytes;
+        chars += count.chars;
+        lines += count.lines;
+        words += count.words;
+        max_line_length = max_line_length.max(count.max_line_length);
+        emoji += count.emoji;
+        whitespace += count.whitespace;
+        sentences += count.sentences;
+        words_no_numbers += count.words_no_numbers;
+        bytes_no_eol += count.bytes_no_eol;
+        blank_lines += count.blank_lines;
+        invalid_utf8 += count.invalid_utf8;
+        tokens += count.tokens;
+        reading_time += count.reading_time;
+        graphemes += count.graphemes;
+        paragraphs += count.paragraphs;
+        for (set, other) in byte_set.iter_mut().zip(count.byte_set) {
+            *set |= other;
+        }
+        word_set.extend(count.word_set.iter().cloned());
+        line_set.add(&count.line_set);
+        if matches.len() < count.matches.len() {
+            matches.resize(count.matches.len(), 0);
+        }
+        for (total, count) in matches.iter_mut().zip(&count.matches) {
+            *total += count;
+        }
+        if let Some(report) = &count.brackets {
+            brackets.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.code_blocks {
+            code_blocks.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.continuations {
+            continuations
+                .get_or_insert_with(Default::default)
+                .add(report);
+        }
+        if let Some(report) = &count.longest_line {
+            LongestLine::add(&mut longest_line, report);
+        }
+        if let Some(report) = &count.line_endings {
+            line_endings
+                .get_or_insert_with(Default::default)
+                .add(report);
+        }
+        if let Some(report) = &count.columns {
+            columns.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.todo {
+            todo.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.urls {
+            urls.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.xml {
+            xml.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.json {
+            json.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.utf8 {
+            utf8.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.ascii {
+            ascii.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.classes {
+            classes.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.stats {
+            stats.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.readability {
+            readability.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.histogram {
+            histogram.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.char_ranges {
+            char_ranges.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.first_chars {
+            first_chars.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.repeats {
+            repeats.get_or_insert_with(Default::default).add(report);
+        }
+        if let Some(report) = &count.frequency {
+            frequency.get_or_insert_with(Default::default).add(report);
+        }
+    });
+
+    let filename = String::from("total");
+    WordCount {
+        filename,
+        bytes,
+        chars,
+        lines,
+        words,
+        max_line_length,
+        emoji,
+        whitespace,
+        sentences,
+        words_no_numbers,
+        bytes_no_eol,
+        blank_lines,
+        invalid_utf8,
+        tokens,
+        reading_time,
+        graphemes,
+        paragraphs,
+        byte_set,
+        word_set,
+        line_set,
+        matches,
+        first_tab: None,
+        bom: None,
+        missing_newline: false,
+        brackets,
+        code_blocks,
+        continuations,
+        longest_line,
+        line_endings,
+        columns,
+        todo,
+        urls,
+        xml,
+        json,
+        utf8,
+        ascii,
+        classes,
+        stats,
+        readability,
+        histogram,
+        char_ranges,
+        first_chars,
+        repeats,
+        frequency,
+        entries: Vec::new(),
+    }
+}
+
+fn print_output(
+    out: &mut impl Write,
+    results: &[Result<WordCount, CountError>],
+    total: &WordCount,
+    args: &Args,
+) -> io::Result<()> {
+    let shown = |wc: &WordCount| only_matches(&args.only, &wc.filename);
+    let filtered_total;
+    let total = if args.only_in_total {
+        filtered_total = self::total(results.iter().flatten().filter(|wc| shown(wc)));
+        &filtered_total
+    } else if args.merge {
+        // A merged count is the total row, with no rows of its own
+        results.iter().flatten().last().unwrap_or(total)
+    } else {
+        total
+    };
+
+    // Find largest value to use as offset to correctly format output
+    let max = args
+        .metrics()
+        .map(|metric| metric.value(total))
+        .max()
+        .unwrap_or(0);
+    let offset = max.to_string().len();
+
+    let percent_of = args.percent.then_some(total);
+
+    let delimited = matches!(args.format, Format::Csv | Format::Tsv) && args.printf.is_none();
+    if delimited && args.header {
+        let mut names = vec![String::from("filename")];
+        names.extend(
+            args.metrics()
+                .map(|metric| args.format.field(&metric.label(args)).into_owned()),
+        );
+        write!(
+            out,
+            "{}{}",
+            names.join(args.format.separator()),
+            args.terminator()
+        )?;
+    }
+
+    // Files that couldn't be counted have nothing to sort by, so go last
+    let mut sorted: Vec<_> = results.iter().collect();
+    if let Some(key) = args.sort {
+        sorted.sort_by(|a, b| match (a, b) {
+            (Ok(a), Ok(b)) if args.reverse => key.compare(b, a),
+            (Ok(a), Ok(b)) => key.compare(a, b),
+            _ => a.is_err().cmp(&b.is_err()),
+        });
+    }
+
+    // Print results; JSON entries are collected to be written as one array
+    let mut entries = Vec::new();
+    for res in sorted {
+        match res {
+            Ok(_) if args.total == Total::Only || args.merge => {}
+            Ok(wc) if shown(wc) => {
+                // The files in an archive come before its subtotal
+                for wc in wc.entries.iter().chain([wc]) {
+                    match (&args.printf, args.format) {
+                        (Some(template), _) => write!(out, "{}", wc.fill(template))?,
+                        (None, Format::Text) => wc.print(out, offset, false, percent_of, args)?,
+                        (None, Format::Json) => entries.push(wc.json(false, args)),
+                        (None, Format::Csv | Format::Tsv) => {
+                            write!(out, "{}{}", wc.record(args.format, args), args.terminator())?
+                        }
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{}", e);
+                if args.format == Format::Json {
+                    entries.push(e.json(args));
+                }
+            }
+        }
+    }
+
+    // Print the total count if there was more than one file as input, unless
+    // --total says otherwise; a merged count is always there to print
+    let print_total = match args.total {
+        Total::Auto => results.len() > 1 || args.merge,
+        Total::Always | Total::Only => true,
+        Total::Never => false,
+    };
+    if print_total {
+        match (&args.printf, args.format) {
+            (Some(template), _) => write!(out, "{}", total.fill(template))?,
+            (None, Format::Text) => total.print(out, offset, true, None, args)?,
+            (None, Format::Json) => entries.push(total.json(true, args)),
+            (None, Format::Csv | Format::Tsv) => write!(
+                out,
+                "{}{}",
+                total.record(args.format, args),
+                args.terminator()
+            )?,
+        }
+    }
+
+    if args.format == Format::Json && args.printf.is_none() {
+        writeln!(out, "[")?;
+        for (i, entry) in entries.iter().enumerate() {
+            let comma = if i + 1 < entries.len() { "," } else { "" };
+            writeln!(out, "  {}{}", entry, comma)?;
+        }
+        writeln!(out, "]")?;
+    }
+    Ok(())
+}
+
+/// Quotes `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Reads from `inner` on a watchdog thread so that a read which sees no data
+/// within `timeout` fails with `io::ErrorKind::TimedOut` instead of blocking.
+struct TimeoutReader {
+    receiver: Receiver<io::Result<Vec<u8>>>,
+    timeout: Duration,
+    chunk: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl TimeoutReader {
+    fn new<R: Read + Send + 'static>(mut inner: R, timeout: Duration) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        thread::spawn(move || loop {
+            let mut buf = vec![0; 64 * 1024];
+            match inner.read(&mut buf) {
+                Ok(0) => {
+                    let _ = sender.send(Ok(Vec::new()));
+                    break;
+                }
+                Ok(n) => {
+                    buf.truncate(n);
+                    if sender.send(Ok(buf)).is_err() {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    let _ = sender.send(Err(e));
+                    break;
+                }
+            }
+        });
+
+        TimeoutReader {
+            receiver,
+            timeout,
+            chunk: Vec::new(),
+            pos: 0,
+            done: false,
+        }
+    }
+}
+
+impl Read for TimeoutReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.chunk.len() {
+            if self.done {
+                return Ok(0);
+            }
+            match self.receiver.recv_timeout(self.timeout) {
+                Ok(Ok(chunk)) if chunk.is_empty() => {
+                    self.done = true;
+                    return Ok(0);
+                }
+                Ok(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                Ok(Err(e)) => return Err(e),
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!(
+                            "no data received within {} seconds",
+                            self.timeout.as_secs_f64()
+                        ),
+                    ));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.done = true;
+                    return Ok(0);
+                }
+            }
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// A text encoding that can be recognised by its byte order mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl Encoding {
+    /// Guesses the encoding from the leading bytes, assuming UTF-8 if there is
+    /// no byte order mark. Returns the encoding and the length of its BOM.
+    fn detect(bytes: &[u8]) -> (Self, usize) {
+        // UTF-32 LE must be checked first since its BOM starts like UTF-16 LE's
+        match bytes {
+            [0xFF, 0xFE, 0x00, 0x00, ..] => (Encoding::Utf32Le, 4),
+            [0x00, 0x00, 0xFE, 0xFF, ..] => (Encoding::Utf32Be, 4),
+            [0xEF, 0xBB, 0xBF, ..] => (Encoding::Utf8Bom, 3),
+            [0xFF, 0xFE, ..] => (Encoding::Utf16Le, 2),
+            [0xFE, 0xFF, ..] => (Encoding::Utf16Be, 2),
+            _ => (Encoding::Utf8, 0),
+        }
+    }
+
+    /// Parses an encoding name such as `utf16le` or `UTF-16LE`.
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+            "utf8" => Some(Encoding::Utf8),
+            "utf8bom" => Some(Encoding::Utf8Bom),
+            "utf16le" => Some(Encoding::Utf16Le),
+            "utf16be" => Some(Encoding::Utf16Be),
+            "utf32le" => Some(Encoding::Utf32Le),
+            "utf32be" => Some(Encoding::Utf32Be),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 with BOM",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf32Le => "UTF-32LE",
+            Encoding::Utf32Be => "UTF-32BE",
+        }
+    }
+
+    /// Decodes `bytes` (without the BOM), replacing malformed sequences with
+    /// U+FFFD.
+    fn decode(self, bytes: &[u8]) -> Cow<'_, str> {
+        let decoded = match self {
+            Encoding::Utf8 | Encoding::Utf8Bom => return String::from_utf8_lossy(bytes),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let units = bytes.chunks_exact(2).map(|b| match self {
+                    Encoding::Utf16Le => u16::from_le_bytes([b[0], b[1]]),
+                    _ => u16::from_be_bytes([b[0], b[1]]),
+                });
+                char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+            Encoding::Utf32Le | Encoding::Utf32Be => bytes
+                .chunks_exact(4)
+                .map(|b| {
+                    let b = [b[0], b[1], b[2], b[3]];
+                    let code = match self {
+                        Encoding::Utf32Le => u32::from_le_bytes(b),
+                        _ => u32::from_be_bytes(b),
+                    };
+                    char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                })
+                .collect(),
+        };
+        Cow::Owned(decoded)
+    }
+}
+
+impl Encoding {
+    /// Encodes `text`, starting with a byte order mark for every encoding
+    /// other than plain UTF-8.
+    fn encode(self, text: &str) -> Vec<u8> {
+        match self {
+            Encoding::Utf8 => text.as_bytes().to_vec(),
+            Encoding::Utf8Bom => {
+                let mut bytes = vec![0xEF, 0xBB, 0xBF];
+                bytes.extend_from_slice(text.as_bytes());
+                bytes
+            }
+            Encoding::Utf16Le | Encoding::Utf16Be => std::iter::once('\u{FEFF}')
+                .chain(text.chars())
+                .flat_map(|c| {
+                    let mut units = [0; 2];
+                    c.encode_utf16(&mut units).to_vec()
+                })
+                .flat_map(|unit| match self {
+                    Encoding::Utf16Le => unit.to_le_bytes(),
+                    _ => unit.to_be_bytes(),
+                })
+                .collect(),
+            Encoding::Utf32Le | Encoding::Utf32Be => std::iter::once('\u{FEFF}')
+                .chain(text.chars())
+                .flat_map(|c| match self {
+                    Encoding::Utf32Le => (c as u32).to_le_bytes(),
+                    _ => (c as u32).to_be_bytes(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Turns the raw bytes of an input into text, reporting the detected
+/// encoding as requested and decoding by it unless `--encoding` names one or
+/// `--no-detect` is given, when the input is taken to be UTF-8. Without
+/// `--no-detect`, a byte order mark of the encoding `--encoding` names isn't
+/// part of the text. Invalid sequences are read as U+FFFD.
+fn decode_input<'a>(name: &str, bytes: &'a [u8], args: &Args) -> Cow<'a, str> {
+    let (encoding, bom) = Encoding::detect(bytes);
+    if args.detect_encoding {
+        eprintln!("wc: {}: detected encoding {}", name, encoding.name());
+    }
+    if let Some(encoding) = args.input_encoding.filter(|_| args.detect_bom) {
+        encoding.decode_with_bom_removal(bytes).0
+    } else if let Some(encoding) = args.input_encoding {
+        encoding.decode_without_bom_handling(bytes).0
+    } else if args.detect_bom {
+        encoding.decode(&bytes[bom..])
+    } else {
+        String::from_utf8_lossy(bytes)
+    }
+}
+
+// How often the progress bar is redrawn
+const PROGRESS_BAR_INTERVAL: Duration = Duration::from_millis(200);
+
+const PROGRESS_BAR_WIDTH: u64 = 30;
+
+/// Progress through the inputs, written to stderr so that stdout only ever
+/// contains the counts: machine-readable records for `--progress-json`, or a
+/// bar on a terminal.
+struct Progress {
+    bytes_done: u64,
+    /// Unknown when reading from standard input
+    bytes_total: Option<u64>,
+    files_done: usize,
+    interval: Duration,
+    last_emit: Instant,
+    /// Whether to draw a bar rather than write JSON records
+    bar: bool,
+    /// Whether the bar is on the terminal and needs clearing at the end
+    drawn: bool,
+    started: Instant,
+    /// What separates the fractions of the sizes on the bar
+    decimal_sep: char,
+    /// Where the records or the bar are written, stderr unless redirected
+    out: Box<dyn Write + Send>,
+}
+
+impl Progress {
+    fn new(bytes_total: Option<u64>, interval: Duration) -> Self {
+        Progress {
+            bytes_done: 0,
+            bytes_total,
+            files_done: 0,
+            interval,
+            last_emit: Instant::now(),
+            bar: false,
+            drawn: false,
+            started: Instant::now(),
+            decimal_sep: '.',
+            out: Box::new(io::stderr()),
+        }
+    }
+
+    fn bar(bytes_total: u64, decimal_sep: char) -> Self {
+        Progress {
+            bar: true,
+            decimal_sep,
+            ..Progress::new(Some(bytes_total), PROGRESS_BAR_INTERVAL)
+        }
+    }
+
+    fn advance(&mut self, bytes: usize) {
+        self.bytes_done += bytes as u64;
+        if self.last_emit.elapsed() >= self.interval {
+            self.emit();
+        }
+    }
+
+    fn emit(&mut self) {
+        if self.bar {
+            self.draw();
+            self.last_emit = Instant::now();
+            return;
+        }
+        let record = self.record();
+        let _ = writeln!(self.out, "{}", record);
+        self.last_emit = Instant::now();
+    }
+
+    /// The progress so far as one line of JSON.
+    fn record(&self) -> String {
+        let bytes_total = self
+            .bytes_total
+            .map_or(String::from("null"), |total| total.to_string());
+        format!(
+            "{{\"bytes_done\":{},\"bytes_total\":{},\"files_done\":{}}}",
+            self.bytes_done, bytes_total, self.files_done
+        )
+    }
+
+    /// Redraws the bar over the last one, as `[###   ] 42% 1.2 GiB/2.9 GiB
+    /// 310.5 MiB/s 0:05 left`.
+    fn draw(&mut self) {
+        let total = self.bytes_total.unwrap_or(0).max(1);
+        let done = self.bytes_done.min(total);
+        let filled = done * PROGRESS_BAR_WIDTH / total;
+        let rate = self.bytes_done as f64 / self.started.elapsed().as_secs_f64();
+        let left = if rate > 0.0 {
+            ((total - done) as f64 / rate) as u64
+        } else {
+            0
+        };
+        let _ = write!(
+            self.out,
+            "\r[{}{}] {:>3}% {}/{} {}/s {} left\x1b[K",
+            "#".repeat(filled as usize),
+            " ".repeat((PROGRESS_BAR_WIDTH - filled) as usize),
+            done * 100 / total,
+            binary_size(done, self.decimal_sep),
+            binary_size(total, self.decimal_sep),
+            binary_size(rate as u64, self.decimal_sep),
+            clock_time(left)
+        );
+        self.drawn = true;
+    }
+
+    /// Ends with a record of the completed work, or clears the bar so the
+    /// counts that follow aren't mixed up with it.
+    fn finish(&mut self) {
+        if !self.bar {
+            self.emit();
+        } else if self.drawn {
+            let _ = write!(self.out, "\r\x1b[K");
+        }
+    }
+}
+
+/// `value` to `places` decimal places, with `sep` before the fraction.
+fn decimal(value: f64, places: usize, sep: char) -> String {
+    let formatted = format!("{:.*}", places, value);
+    match sep {
+        '.' => formatted,
+        _ => formatted.replacen('.', sep.encode_utf8(&mut [0; 4]), 1),
+    }
+}
+
+/// The decimal separator of the `LC_NUMERIC` locale that `LC_ALL`,
+/// `LC_NUMERIC` or `LANG` names, or '.' if it can't be loaded.
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd"))]
+fn locale_decimal_sep() -> char {
+    // SAFETY: the locale is only made current on this thread, and is put back
+    // once the separator has been copied out of it
+    unsafe {
+        let locale = libc::newlocale(libc::LC_NUMERIC_MASK, c"".as_ptr(), std::ptr::null_mut());
+        if locale.is_null() {
+            return '.';
+        }
+        let previous = libc::uselocale(locale);
+        let point = std::ffi::CStr::from_ptr((*libc::localeconv()).decimal_point);
+        let sep = point.to_str().ok().and_then(|point| point.chars().next());
+        libc::uselocale(previous);
+        libc::freelocale(locale);
+        sep.unwrap_or('.')
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "freebsd")))]
+fn locale_decimal_sep() -> char {
+    '.'
+}
+
+/// `bytes` in the largest binary unit that keeps it at least 1, such as
+/// `1.5 MiB`, with `sep` before the fraction.
+fn binary_size(bytes: u64, sep: char) -> String {
+    const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{} {}", decimal(size, 1, sep), UNITS[unit])
+}
+
+/// `secs` as `M:SS`, or `H:MM:SS` from an hour up.
+fn clock_time(secs: u64) -> String {
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{}:{:02}", minutes, secs)
+    }
+}
+
+/// Counts everything read through it, so that another reader, such as the
+/// XML parser, can pull the input while it's counted.
+struct CountingReader<'a, R> {
+    inner: R,
+    counter: &'a mut Counter,
+    filename: &'a str,
+    args: &'a Args,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf);
+        if status_requested() {
+            write_status(self.counter.clone(), self.filename, self.args);
+        }
+        let n = read?;
+        self.counter.update(&buf[..n])?;
+        Ok(n)
+    }
+}
+
+/// Reports every successful read to a `Progress`.
+struct ProgressReader<'a, R> {
+    inner: R,
+    progress: &'a mut Progress,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.advance(n);
+        Ok(n)
+    }
+}
+
+/// Wraps `reader` to honour `--read-timeout`, report to `progress` and, with
+/// `--decompress`, decompress it.
+fn wrap_reader<'a, R: Read + Send + 'static>(
+    reader: R,
+    progress: Option<&'a mut Progress>,
+    args: &Args,
+) -> io::Result<Box<dyn Read + 'a>> {
+    let mut reader: Box<dyn Read + 'a> = match args.read_timeout {
+        Some(timeout) => Box::new(TimeoutReader::new(reader, timeout)),
+        None => Box::new(reader),
+    };
+    if let Some(progress) = progress {
+        reader = Box::new(ProgressReader {
+            inner: reader,
+            progress,
+        });
+    }
+    if args.decompress {
+        reader = decompress(reader)?;
+    }
+    Ok(reader)
+}
+
+/// Decompresses `reader` if it starts like a gzip, zstd, xz or bzip2 stream,
+/// including several streams one after the other as `cat` would join them.
+fn decompress<'a>(reader: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+    let reader = peek(reader, 6)?;
+    let magic = reader.get_ref().0.get_ref();
+    Ok(if magic.starts_with(&[0x1f, 0x8b]) {
+        Box::new(flate2::read::MultiGzDecoder::new(reader))
+    } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Box::new(zstd::stream::read::Decoder::new(reader)?)
+    } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0]) {
+        Box::new(xz2::read::XzDecoder::new_multi_decoder(reader))
+    } else if magic.starts_with(b"BZh") {
+        Box::new(bzip2::read::MultiBzDecoder::new(reader))
+    } else {
+        Box::new(reader)
+    })
+}
+
+/// Reads the first `n` bytes of `reader` and puts them back in front of the
+/// rest, so the start can be looked at with `get_ref` before counting.
+fn peek<R: Read>(mut reader: R, n: u64) -> io::Result<io::Chain<io::Cursor<Vec<u8>>, R>> {
+    let mut head = Vec::new();
+    (&mut reader).take(n).read_to_end(&mut head)?;
+    Ok(io::Cursor::new(head).chain(reader))
+}
+
+/// Reads all of `reader`, honouring `--read-timeout` if set and reporting to
+/// `progress` as data arrives. `size` pre-sizes the buffer when the length of
+/// the input is known, so large files don't cause repeated reallocations: the
+/// wrappers hide the size `File` would otherwise give `read_to_end` (see
+/// benches/presize.rs).
+fn read_input<R: Read + Send + 'static>(
+    reader: R,
+    size: Option<u64>,
+    progress: Option<&mut Progress>,
+    args: &Args,
+) -> io::Result<Vec<u8>> {
+    let mut reader = wrap_reader(reader, progress, args)?;
+    let capacity = size
+        .and_then(|size| usize::try_from(size).ok())
+        .unwrap_or(0);
+    let mut buffer = Vec::with_capacity(capacity);
+    reader.read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Counts one input, streaming it in constant memory when only the basic
+/// counts, `--xml` or `--json-stats` are needed and reading it whole
+/// otherwise.
+fn count_input<R: Read + Send + 'static>(
+    filename: String,
+    reader: R,
+    size: Option<u64>,
+    progress: Option<&mut Progress>,
+    args: &Args,
+) -> io::Result<WordCount> {
+    if !args.streams() {
+        let buffer = read_input(reader, size, progress, args)?;
+        return count_bytes(filename, &buffer, None, args);
+    }
+
+    // The counter looks for a byte order mark itself, so that nothing waits on
+    // the start of the input before the updates begin
+    let reader = wrap_reader(reader, progress, args)?;
+    let mut counter = Counter::for_args(args);
+    let mut xml = None;
+    if args.xml {
+        xml = Some(counter.read_xml(reader, &filename, args)?);
+    } else if let Some(interval) = args.interval {
+        counter = read_with_updates(counter, reader, &filename, interval, args)?;
+    } else {
+        counter.read_reporting(reader, &filename, args)?;
+    }
+    let mut wc = counter.finish(filename, args)?;
+    // Decoded input has had its XML read from the decoded text
+    wc.xml = wc.xml.or(xml);
+    Ok(wc)
+}
+
+/// Counts all of `reader` into `counter` for `--interval`, while another
+/// thread writes the running counts to stderr every `interval`, so that they
+/// show even when a read blocks.
+fn read_with_updates(
+    counter: Counter,
+    mut reader: impl Read,
+    filename: &str,
+    interval: Duration,
+    args: &Args,
+) -> io::Result<Counter> {
+    let counter = Mutex::new(counter);
+    let (done, finished) = mpsc::channel::<()>();
+
+    thread::scope(|scope| {
+        let counter = &counter;
+        scope.spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(interval) {
+                let running = counter.lock().unwrap().clone();
+                write_status(running, filename, args);
+            }
+        });
+
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let result = loop {
+            let read = reader.read(&mut chunk);
+            if status_requested() {
+                let running = counter.lock().unwrap().clone();
+                write_status(running, filename, args);
+            }
+            match read {
+                Ok(0) => break Ok(()),
+                Ok(n) => {
+                    if let Err(e) = counter.lock().unwrap().update(&chunk[..n]) {
+                        break Err(e);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        drop(done);
+        result
+    })?;
+    Ok(counter.into_inner().unwrap())
+}
+
+/// Writes the counts of `filename` so far, as kept by `counter`, to stderr.
+fn write_status(counter: Counter, filename: &str, args: &Args) {
+    if let Ok(wc) = counter.finish(filename.to_string(), args) {
+        let results = [Ok(wc)];
+        let total = total(results.iter().flatten());
+        let _ = io::stderr().write_all(&report(&results, &total, args));
+    }
+}
+
+/// Set by a status signal, until the running counts are written.
+static STATUS_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether a status signal came since this was last asked.
+fn status_requested() -> bool {
+    STATUS_REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+/// Has SIGUSR1, and SIGINFO on the BSDs and macOS, ask for the running
+/// counts as with dd. Reads are interrupted by them rather than restarted, so
+/// that a request is answered even while a read is blocked.
+#[cfg(unix)]
+fn handle_status_signals() {
+    extern "C" fn request_status(_: libc::c_int) {
+        STATUS_REQUESTED.store(true, Ordering::Relaxed);
+    }
+
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    let signals = [libc::SIGUSR1, libc::SIGINFO];
+    #[cfg(not(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    )))]
+    let signals = [libc::SIGUSR1];
+
+    // SAFETY: the handler only stores to an atomic, which is signal safe
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = request_status as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        for signal in signals {
+            libc::sigaction(signal, &action, std::ptr::null_mut());
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn handle_status_signals() {}
+
+/// Counts an input that is already in memory, such as a mapped file,
+/// reporting to `progress` as it goes.
+fn count_bytes(
+    filename: String,
+    bytes: &[u8],
+    mut progress: Option<&mut Progress>,
+    args: &Args,
+) -> io::Result<WordCount> {
+    if !args.streams() || args.decodes(bytes) {
+        if let Some(progress) = progress {
+            progress.advance(bytes.len());
+        }
+        let text = decode_input(display_name(&filename), bytes, args);
+        return Ok(WordCount::parse(filename, bytes, &text, args));
+    }
+
+    let mut counter = Counter::for_args(args);
+    let xml = args.xml.then(|| XmlReport::read(bytes)).transpose()?;
+    // A single large file is split between the threads --jobs would have used
+    let single_file = args.files.len() == 1;
+    // JSON can't be scanned from the middle, so it's counted in one
+    let splits = counter.basic && counter.json.is_none();
+    if splits && single_file && args.jobs > 1 && bytes.len() as u64 >= MMAP_THRESHOLD {
+        counter = count_split(bytes, args.jobs, args)?;
+        if let Some(progress) = progress {
+            progress.advance(bytes.len());
+        }
+    } else {
+        for chunk in bytes.chunks(CHUNK_SIZE) {
+            counter.update(chunk)?;
+            if let Some(progress) = progress.as_deref_mut() {
+                progress.advance(chunk.len());
+            }
+            if status_requested() {
+                write_status(counter.clone(), &filename, args);
+            }
+        }
+    }
+    let mut wc = counter.finish(filename, args)?;
+    wc.xml = xml;
+    Ok(wc)
+}
+
+/// Counts the basic counts of `bytes` on `jobs` threads, each taking an equal
+/// slice, and joins the results.
+fn count_split(bytes: &[u8], jobs: usize, args: &Args) -> io::Result<Counter> {
+    // Slices start on a character boundary so no UTF-8 sequence is cut in two
+    let mut starts = vec![0];
+    for i in 1..jobs {
+        let mut start = bytes.len() / jobs * i;
+        while bytes.get(start).is_some_and(|b| b & 0xC0 == 0x80) {
+            start += 1;
+        }
+        if start > starts[starts.len() - 1] && start < bytes.len() {
+            starts.push(start);
+        }
+    }
+    let ends = starts.iter().skip(1).copied().chain([bytes.len()]);
+    let parts: Vec<&[u8]> = starts
+        .iter()
+        .zip(ends)
+        .map(|(&s, e)| &bytes[s..e])
+        .collect();
+
+    let counters = thread::scope(|scope| {
+        let workers: Vec<_> = parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| {
+                scope.spawn(move || {
+                    let mut counter = Counter::for_args(args);
+                    // Only the first slice starts at the start of the input
+                    counter.skip_bom &= i == 0;
+                    for chunk in part.chunks(CHUNK_SIZE) {
+                        counter.update(chunk)?;
+                    }
+                    Ok(counter)
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect::<io::Result<Vec<_>>>()
+    })?;
+
+    let mut counters = counters.into_iter().zip(parts);
+    let (mut counter, _) = counters.next().expect("There is always one slice");
+    for (next, part) in counters {
+        let first = String::from_utf8_lossy(&part[..part.len().min(4)]);
+        let starts_in_word = first.chars().next().is_some_and(|c| !c.is_whitespace());
+        counter.join(next, starts_in_word);
+    }
+    Ok(counter)
+}
+
+/// Maps `file` into memory, or returns `None` to fall back to reading it.
+fn map_file(file: &File) -> Option<Mmap> {
+    // SAFETY: the map is only read, but it is undefined behaviour if another
+    // process truncates or rewrites the file while it is counted. That risk is
+    // why only large regular files are mapped unless --mmap is given.
+    unsafe { Mmap::map(file) }.ok()
+}
+
+/// Counts the named file, or standard input for `-`.
+fn count_file(
+    file: &str,
+    progress: Option<&mut Progress>,
+    args: &Args,
+) -> Result<WordCount, CountError> {
+    if file.starts_with("http://") || file.starts_with("https://") {
+        return count_url(file, progress, args).map_err(|e| io_error(file, &e));
+    }
+    let result = if file == "-" {
+        count_input(file.to_string(), io::stdin(), None, progress, args)
+    } else {
+        let f = File::open(file).map_err(|e| io_error(file, &e))?;
+        // Pipes and special files report a size of 0, which is just no hint
+        let metadata = f.metadata().ok();
+        if metadata.as_ref().is_some_and(Metadata::is_dir) {
+            return Err(CountError {
+                filename: file.to_string(),
+                message: String::from("Is a directory"),
+            });
+        }
+        if args.archive {
+            match count_archive(file, args) {
+                Ok(Some(wc)) => return Ok(wc),
+                Ok(None) => {}
+                Err(e) => return Err(io_error(file, &e)),
+            }
+        }
+        let size = metadata.as_ref().map(|m| m.len());
+        // The size of a regular file is its byte count, so there's no need to
+        // read it when that's all that's asked for
+        if let Some(size) = size.filter(|&size| size > 0 && args.only_bytes()) {
+            if metadata.as_ref().is_some_and(Metadata::is_file) {
+                if let Some(progress) = progress {
+                    progress.advance(size as usize);
+                }
+                let counter = Counter {
+                    bytes: size,
+                    ..Counter::for_args(args)
+                };
+                return counter
+                    .finish(file.to_string(), args)
+                    .map_err(|e| io_error(file, &e));
+            }
+        }
+        match metadata.filter(|m| args.maps(m)).and_then(|_| map_file(&f)) {
+            Some(map) => count_bytes(file.to_string(), &map, progress, args),
+            None => count_input(file.to_string(), f, size, progress, args),
+        }
+    };
+    result.map_err(|e| io_error(file, &e))
+}
+
+/// The message for an I/O error on `name`, such as
+/// "wc: FILE: Permission denied".
+fn io_error(name: &str, e: &io::Error) -> CountError {
+    CountError {
+        filename: name.to_string(),
+        message: describe(e),
+    }
+}
+
+/// Why a file couldn't be counted, written as `wc: FILE: MESSAGE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CountError {
+    filename: String,
+    message: String,
+}
+
+impl CountError {
+    /// The error as a JSON object, in place of the counts of its file.
+    fn json(&self, args: &Args) -> String {
+        let mut fields = [
+            ("filename", json_string(&self.filename)),
+            ("error", json_string(&self.message)),
+        ];
+        if args.sort_json_keys {
+            fields.sort();
+        }
+        let fields: Vec<String> = fields
+            .iter()
+            .map(|(key, value)| format!("{}: {}", json_string(key), value))
+            .collect();
+        format!("{{{}}}", fields.join(", "))
+    }
+}
+
+impl std::fmt::Display for CountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "wc: {}: {}", self.filename, self.message)
+    }
+}
+
+/// Describes `e` without the error code that Rust adds to OS errors.
+fn describe(e: &io::Error) -> String {
+    let mut message = e.to_string();
+    if let Some(end) = message
+        .find(" (os error ")
+        .filter(|_| e.raw_os_error().is_some())
+    {
+        message.truncate(end);
+    }
+    message
+}
+
+/// Fetches and counts the body of an `http` or `https` URL.
+#[cfg(feature = "http")]
+fn count_url(url: &str, progress: Option<&mut Progress>, args: &Args) -> io::Result<WordCount> {
+    let response = ureq::get(url).call().map_err(io::Error::other)?;
+    let reader = response.into_body().into_reader();
+    count_input(url.to_string(), reader, None, progress, args)
+}
+
+#[cfg(not(feature = "http"))]
+fn count_url(_: &str, _: Option<&mut Progress>, _: &Args) -> io::Result<WordCount> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "URLs can only be read when built with the http feature",
+    ))
+}
+
+/// Counts each regular file inside `file` if it is a zip or POSIX tar
+/// archive, which may be gzip-compressed, returning `None` if it is neither.
+/// The archive's own counts are the sum of its files'.
+fn count_archive(file: &str, args: &Args) -> io::Result<Option<WordCount>> {
+    let mut head = Vec::with_capacity(512);
+    File::open(file)?.take(512).read_to_end(&mut head)?;
+    let gzipped = head.starts_with(&[0x1f, 0x8b]);
+    if gzipped {
+        let decoder = flate2::read::GzDecoder::new(File::open(file)?);
+        head.clear();
+        if decoder.take(512).read_to_end(&mut head).is_err() {
+            return Ok(None);
+        }
+    }
+
+    let mut entries = Vec::new();
+    let entry_name = |name: &str| format!("{}:{}", file, name);
+    if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") {
+        let mut zip = zip::ZipArchive::new(File::open(file)?).map_err(io::Error::other)?;
+        for i in 0..zip.len() {
+            let entry = zip.by_index(i).map_err(io::Error::other)?;
+            if entry.is_file() {
+                let name = entry_name(&entry.name().map_err(io::Error::other)?);
+                entries.push(count_entry(name, entry, args)?);
+            }
+        }
+    } else if head.get(257..262) == Some(b"ustar") {
+        let reader: Box<dyn Read> = if gzipped {
+            Box::new(flate2::read::MultiGzDecoder::new(File::open(file)?))
+        } else {
+            Box::new(File::open(file)?)
+        };
+        let mut tar = tar::Archive::new(reader);
+        for entry in tar.entries()? {
+            let entry = entry?;
+            if entry.header().entry_type().is_file() {
+                let name = entry_name(&entry.path()?.to_string_lossy());
+                entries.push(count_entry(name, entry, args)?);
+            }
+        }
+    } else {
+        return Ok(None);
+    }
+
+    let mut archive = total(&entries);
+    archive.filename = file.to_string();
+    archive.entries = entries;
+    Ok(Some(archive))
+}
+
+/// Counts an input that can only be read through once, such as a file
+/// inside an archive.
+fn count_entry(filename: String, reader: impl Read, args: &Args) -> io::Result<WordCount> {
+    let mut reader: Box<dyn Read> = Box::new(reader);
+    if args.decompress {
+        reader = decompress(reader)?;
+    }
+    let mut reader = peek(reader, 4)?;
+    if !args.streams() || args.decodes(reader.get_ref().0.get_ref()) {
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer)?;
+        return count_bytes(filename, &buffer, None, args);
+    }
+    let mut counter = Counter::for_args(args);
+    let mut xml = None;
+    if args.xml {
+        xml = Some(counter.read_xml(reader, &filename, args)?);
+    } else {
+        counter.read_reporting(reader, &filename, args)?;
+    }
+    let mut wc = counter.finish(filename, args)?;
+    wc.xml = xml;
+    Ok(wc)
+}
+
+/// Counts `files` on up to `jobs` threads, returning the results in the
+/// order of `files`. `progress` moves on as each file is finished.
+fn count_parallel(
+    files: &[String],
+    jobs: usize,
+    progress: Option<&mut Progress>,
+    args: &Args,
+) -> Vec<Result<WordCount, CountError>> {
+    let next = AtomicUsize::new(0);
+    let progress = progress.map(Mutex::new);
+    let mut counted: Vec<_> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.min(files.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut counted = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(file) = files.get(i) else {
+                            break counted;
+                        };
+                        let result = count_file(file, None, args);
+                        if let (Some(progress), Ok(wc)) = (&progress, &result) {
+                            let mut progress = progress.lock().unwrap();
+                            progress.files_done += 1;
+                            progress.advance(wc.bytes as usize);
+                        }
+                        counted.push((i, result));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect()
+    });
+    counted.sort_by_key(|&(i, _)| i);
+    counted.into_iter().map(|(_, result)| result).collect()
+}
+
+fn count(args: &Args) -> Vec<Result<WordCount, CountError>> {
+    count_reporting_to(args, Box::new(io::stderr()))
+}
+
+/// Like `count`, but writes any progress to `progress_out`.
+fn count_reporting_to(
+    args: &Args,
+    progress_out: Box<dyn Write + Send>,
+) -> Vec<Result<WordCount, CountError>> {
+    let mut results: Vec<Result<WordCount, CountError>> = Vec::new();
+    let reads_stdin = args.files.iter().any(|file| file == "-");
+    let sizes = || {
+        args.files
+            .iter()
+            .map(|file| std::fs::metadata(file).map_or(0, |m| m.len()))
+            .sum::<u64>()
+    };
+    let mut progress = if args.progress_json {
+        let bytes_total = (!args.files.is_empty()).then(sizes);
+        Some(Progress {
+            out: progress_out,
+            ..Progress::new(bytes_total, args.progress_interval)
+        })
+    } else if args.progress_bar
+        && args.interval.is_none()
+        && !args.files.is_empty()
+        && !reads_stdin
+        && io::stderr().is_terminal()
+    {
+        // Without a size there's nothing to show progress towards
+        Some(sizes()).filter(|&size| size > 0).map(|size| Progress {
+            out: progress_out,
+            ..Progress::bar(size, args.decimal_sep)
+        })
+    } else {
+        None
+    };
+
+    // With no FILE, or when FILE is -, read standard input. A terminal is read
+    // until end of file like anything else: Ctrl-D at the start of a line, or
+    // twice in a row after some text
+    if !args.operands {
+        match count_input(String::new(), io::stdin(), None, progress.as_mut(), args) {
+            Ok(wc) => {
+                results.push(Ok(wc));
+                if let Some(progress) = progress.as_mut() {
+                    progress.files_done += 1;
+                }
+            }
+            Err(e) => results.push(Err(io_error(display_name(""), &e))),
+        }
+    } else if args.merge {
+        // With --merge, inputs are joined so words and lines can span files
+        let mut merged_raw = Vec::new();
+        let mut merged_text = String::new();
+
+        for file in &args.files {
+            let buffer = if file == "-" {
+                read_input(io::stdin(), None, progress.as_mut(), args)
+            } else {
+                let f = match File::open(file) {
+                    Ok(f) => f,
+                    Err(e) => {
+                        results.push(Err(io_error(file, &e)));
+                        continue;
+                    }
+                };
+                let metadata = f.metadata().ok();
+                if metadata.as_ref().is_some_and(Metadata::is_dir) {
+                    results.push(Err(CountError {
+                        filename: file.to_string(),
+                        message: String::from("Is a directory"),
+                    }));
+                    continue;
+                }
+                let size = metadata.map(|m| m.len());
+                read_input(f, size, progress.as_mut(), args)
+            };
+            match buffer {
+                Ok(buffer) => {
+                    let text = decode_input(file, &buffer, args);
+                    merged_text.push_str(&text);
+                    merged_raw.extend_from_slice(&buffer);
+                }
+                Err(e) => {
+                    results.push(Err(io_error(file, &e)));
+                    continue;
+                }
+            }
+            if let Some(progress) = progress.as_mut() {
+                progress.files_done += 1;
+            }
+        }
+
+        let result = WordCount::parse("total".to_string(), &merged_raw, &merged_text, args);
+        results.push(Ok(result));
+    } else if args.jobs > 1 && !args.detect_encoding && !reads_stdin {
+        // Encoding reports are written in file order, and standard input can
+        // only be read by one file at a time
+        results = count_parallel(&args.files, args.jobs, progress.as_mut(), args);
+    } else {
+        for file in &args.files {
+            let result = count_file(file, progress.as_mut(), args);
+            if let Some(progress) = progress.as_mut().filter(|_| result.is_ok()) {
+                progress.files_done += 1;
+            }
+            results.push(result);
+        }
+    }
+
+    if let Some(progress) = progress.as_mut() {
+        progress.finish();
+    }
+    results
+}
+
+/// A file counted by `--follow`, from where the last poll left off.
+#[derive(Debug)]
+struct Followed {
+    name: String,
+    file: Option<File>,
+    counter: Counter,
+    error: Option<CountError>,
+}
+
+impl Followed {
+    fn new(name: &str, args: &Args) -> Self {
+        Followed {
+            name: name.to_string(),
+            file: None,
+            counter: Counter::for_args(args),
+            error: None,
+        }
+    }
+
+    /// Counts whatever was added to the file since the last poll, returning
+    /// whether its counts changed. A new problem is written to stderr.
+    fn poll(&mut self, args: &Args) -> bool {
+        let before = self.counter.bytes;
+        let result = self.read(args);
+        let error = result.err().map(|e| io_error(&self.name, &e));
+        let changed = error != self.error || self.counter.bytes != before;
+        if let Some(e) = error.as_ref().filter(|_| changed) {
+            eprintln!("{}", e);
+        }
+        self.error = error;
+        changed
+    }
+
+    fn read(&mut self, args: &Args) -> io::Result<()> {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self.file.insert(File::open(&self.name)?),
+        };
+        // A file that shrank was truncated or replaced, so count it afresh
+        if file.metadata()?.len() < self.counter.bytes {
+            file.seek(io::SeekFrom::Start(0))?;
+            self.counter = Counter::for_args(args);
+        }
+        self.counter.read_from(file)
+    }
+
+    fn counts(&self, args: &Args) -> Option<WordCount> {
+        if self.error.is_some() {
+            return None;
+        }
+        // Counting goes on, so finish a copy
+        self.counter.clone().finish(self.name.clone(), args).ok()
+    }
+}
+
+/// Counts the FILEs for `--follow`, writing the report again each time they
+/// grow. On a terminal the report is redrawn over the last one.
+fn follow(args: &Args) -> i32 {
+    if args.files.is_empty() || args.files.iter().any(|file| file == "-") {
+        eprintln!("wc: --follow can't follow standard input");
+        return 1;
+    }
+    if !args.streams() {
+        eprintln!("wc: --follow can't be used with counts that need the whole input");
+        return 1;
+    }
+    if args.xml {
+        eprintln!("wc: --follow can't be used with --xml");
+        return 1;
+    }
+    let mut followed: Vec<Followed> = args
+        .files
+        .iter()
+        .map(|file| Followed::new(file, args))
+        .collect();
+    let redraw = io::stdout().is_terminal();
+    let mut drawn = None;
+
+    loop {
+        let mut changed = drawn.is_none();
+        for file in &mut followed {
+            changed |= file.poll(args);
+        }
+        if changed {
+            let results: Vec<_> = followed
+                .iter()
+                .filter_map(|file| file.counts(args).map(Ok))
+                .collect();
+            let total = total(results.iter().flatten());
+            let report = report(&results, &total, args);
+
+            let mut out = io::stdout().lock();
+            if let Some(lines) = drawn.filter(|&lines| redraw && lines > 0) {
+                // Move up to the start of the last report and clear it
+                let _ = write!(out, "\x1b[{}A\x1b[J", lines);
+            }
+            if let Err(e) = out.write_all(&report).and_then(|_| out.flush()) {
+                if e.kind() != io::ErrorKind::BrokenPipe {
+                    eprintln!("wc: write error: {}", e);
+                }
+                return 1;
+            }
+            drawn = Some(report.iter().filter(|&&b| b == b'\n').count());
+        }
+        thread::sleep(args.sleep_interval);
+    }
+}
+
+/// The state of the `--tui` table: the counts of each FILE as they come in,
+/// and how the rows are sorted.
+#[cfg(feature = "tui")]
+struct Dashboard<'a> {
+    args: &'a Args,
+    metrics: Vec<Metric>,
+    /// Each FILE's counts, once they are done
+    results: Vec<Option<Result<WordCount, CountError>>>,
+    /// The column picked with the arrow keys, 0 being the file names
+    column: usize,
+    /// The column the rows are sorted by, if any
+    sort: Option<usize>,
+    reverse: bool,
+    table: ratatui::widgets::TableState,
+}
+
+#[cfg(feature = "tui")]
+impl<'a> Dashboard<'a> {
+    fn new(args: &'a Args) -> Self {
+        Dashboard {
+            args,
+            metrics: args.metrics().collect(),
+            results: args.files.iter().map(|_| None).collect(),
+            column: 0,
+            sort: None,
+            reverse: false,
+            table: Default::default(),
+        }
+    }
+
+    /// Shows the counts as they arrive on `counted` until the user quits,
+    /// returning whether every FILE was counted by then.
+    fn run(
+        &mut self,
+        terminal: &mut ratatui::DefaultTerminal,
+        counted: &Receiver<(usize, Result<WordCount, CountError>)>,
+    ) -> io::Result<bool> {
+        use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+
+        loop {
+            while let Ok((i, result)) = counted.try_recv() {
+                self.results[i] = Some(result);
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if !event::poll(Duration::from_millis(100))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    return Ok(self.results.iter().all(Option::is_some));
+                }
+                KeyCode::Left => self.column = self.column.saturating_sub(1),
+                KeyCode::Right => self.column = (self.column + 1).min(self.metrics.len()),
+                KeyCode::Up => self.table.select_previous(),
+                KeyCode::Down => self.table.select_next(),
+                // Sorting by the same column again turns the order around
+                KeyCode::Char('s') if self.sort == Some(self.column) => {
+                    self.reverse = !self.reverse;
+                }
+                KeyCode::Char('s') => {
+                    self.sort = Some(self.column);
+                    // Counts are most useful biggest first, and names A to Z
+                    self.reverse = self.column > 0;
+                }
+                KeyCode::Char('r') => self.reverse = !self.reverse,
+                _ => {}
+            }
+        }
+    }
+
+    /// The rows in the order they're shown. Files still being counted, and
+    /// failed ones, go after the rest.
+    fn order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.results.len()).collect();
+        let Some(column) = self.sort else {
+            return order;
+        };
+        let counted = |i: usize| self.results[i].as_ref().and_then(|r| r.as_ref().ok());
+        order.sort_by(|&a, &b| match (counted(a), counted(b)) {
+            (Some(a), Some(b)) => {
+                let ordering = match column {
+                    0 => a.filename.cmp(&b.filename),
+                    _ => {
+                        let metric = self.metrics[column - 1];
+                        metric.value(a).cmp(&metric.value(b))
+                    }
+                };
+                if self.reverse {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        });
+        order
+    }
+
+    fn draw(&mut self, frame: &mut ratatui::Frame) {
+        use ratatui::layout::{Constraint, Layout};
+        use ratatui::style::{Style, Stylize};
+        use ratatui::text::Line;
+        use ratatui::widgets::{Cell, Row, Table};
+
+        let total = total(self.results.iter().flatten().flatten());
+        let labels: Vec<_> = std::iter::once(Cow::Borrowed("file"))
+            .chain(self.metrics.iter().map(|metric| metric.label(self.args)))
+            .collect();
+
+        let header = labels.iter().enumerate().map(|(i, label)| {
+            let arrow = match (self.sort == Some(i), self.reverse) {
+                (false, _) => "",
+                (true, false) => " ▲",
+                (true, true) => " ▼",
+            };
+            let cell = Line::from(format!("{}{}", label, arrow));
+            let cell = if i == 0 { cell } else { cell.right_aligned() };
+            let style = if i == self.column {
+                Style::new().bold().reversed()
+            } else {
+                Style::new().bold()
+            };
+            Cell::from(cell).style(style)
+        });
+        let counts = |filename: &str, wc: &WordCount| {
+            let values = self
+                .metrics
+                .iter()
+                .map(|metric| Cell::from(Line::from(metric.value(wc).to_string()).right_aligned()));
+            Row::new(std::iter::once(Cell::from(filename.to_string())).chain(values))
+        };
+        let rows = self.order().into_iter().map(|i| match &self.results[i] {
+            Some(Ok(wc)) => counts(&wc.filename, wc),
+            Some(Err(e)) => Row::new([
+                Cell::from(self.args.files[i].as_str()),
+                Cell::from(format!("{}: {}", e.filename, e.message)),
+            ])
+            .red(),
+            None => Row::new([Cell::from(self.args.files[i].as_str())]).dim(),
+        });
+
+        // Every count fits under its label and is no wider than the total
+        let widths = labels.iter().enumerate().map(|(i, label)| match i {
+            0 => Constraint::Fill(1),
+            _ => {
+                let total = self.metrics[i - 1].value(&total).to_string();
+                Constraint::Length(label.chars().count().max(total.len()) as u16 + 2)
+            }
+        });
+        let table = Table::new(rows, widths)
+            .header(Row::new(header))
+            .footer(counts("total", &total).bold())
+            .row_highlight_style(Style::new().reversed());
+
+        let done = self.results.iter().filter(|r| r.is_some()).count();
+        let status = format!(
+            "{}/{} files  ←/→ column  s sort  r reverse  ↑/↓ scroll  q quit",
+            done,
+            self.results.len()
+        );
+
+        let [table_area, status_area] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+        frame.render_widget(Line::from(status).dim(), status_area);
+    }
+}
+
+/// Counts the FILEs for `--tui` on `--jobs` threads while the table of their
+/// counts is shown. Gives back the results if every FILE was counted before
+/// the user quit.
+#[cfg(feature = "tui")]
+fn tui(args: &Args) -> io::Result<Option<Vec<Result<WordCount, CountError>>>> {
+    if args.files.is_empty() || args.files.iter().any(|file| file == "-") {
+        return Err(io::Error::other("--tui can't count standard input"));
+    }
+    if !io::stdout().is_terminal() {
+        return Err(io::Error::other("--tui needs a terminal"));
+    }
+    let (sender, counted) = mpsc::channel();
+    let next = AtomicUsize::new(0);
+    let quit = AtomicBool::new(false);
+    let mut dashboard = Dashboard::new(args);
+
+    let mut terminal = ratatui::try_init()?;
+    let finished = thread::scope(|scope| {
+        for _ in 0..args.jobs.clamp(1, args.files.len()) {
+            let (sender, next, quit) = (sender.clone(), &next, &quit);
+            scope.spawn(move || {
+                while !quit.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(file) = args.files.get(i) else {
+                        break;
+                    };
+                    let _ = sender.send((i, count_file(file, None, args)));
+                }
+            });
+        }
+        let finished = dashboard.run(&mut terminal, &counted);
+        // Files being counted are finished, but no more are started
+        quit.store(true, Ordering::Relaxed);
+        finished
+    });
+    ratatui::restore();
+
+    Ok(finished?.then(|| dashboard.results.into_iter().flatten().collect()))
+}
+
+#[cfg(not(feature = "tui"))]
+fn tui(_: &Args) -> io::Result<Option<Vec<Result<WordCount, CountError>>>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--tui is only available when built with the tui feature",
+    ))
+}
+
+/// The report of `results`, in the output encoding. It's built up front so
+/// it can be transcoded as a whole.
+fn report(results: &[Result<WordCount, CountError>], total: &WordCount, args: &Args) -> Vec<u8> {
+    let mut report = Vec::new();
+    print_output(&mut report, results, total, args).expect("Writing to a Vec can't fail");
+    args.output_encoding
+        .encode(&String::from_utf8_lossy(&report))
+}
+
+/// Counts the inputs named by `args` and writes the report to stdout and any
+/// problems to stderr, returning the exit status.
+pub fn run(args: &Args) -> i32 {
+    handle_status_signals();
+    if args.follow {
+        return follow(args);
+    }
+    let results = if args.tui {
+        match tui(args) {
+            Ok(Some(results)) => results,
+            // Quitting before the end leaves nothing to print
+            Ok(None) => return 1,
+            Err(e) => {
+                eprintln!("wc: {}", e);
+                return 1;
+            }
+        }
+    } else {
+        count(args)
+    };
+    let total = total(results.iter().flatten());
+
+    let report = report(&results, &total, args);
+    if let Some(path) = &args.output {
+        if let Err(e) = write_output(path, &report, args) {
+            eprintln!("{}", io_error(path, &e));
+            return 1;
+        }
+    } else if let Err(e) = io::stdout().write_all(&report) {
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            eprintln!("wc: write error: {}", e);
+            return 1;
+        }
+    }
+    for (message, _) in problems(&results, args) {
+        eprintln!("{}", message);
+    }
+    status(&results, &total, args)
+}
+
+/// Writes `report` to the `--output` file at `path`. With `--append` it goes
+/// after what the file held, without a second byte order mark, and on a line
+/// of its own.
+fn write_output(path: &str, report: &[u8], args: &Args) -> io::Result<()> {
+    if !args.append {
+        return std::fs::write(path, report);
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+    let len = file.metadata()?.len();
+    if len == 0 {
+        return file.write_all(report);
+    }
+
+    let (_, bom) = Encoding::detect(report);
+    let newline = args.output_encoding.encode("\n");
+    let newline = &newline[Encoding::detect(&newline).1..];
+    let mut end = vec![0; newline.len()];
+    file.seek(io::SeekFrom::Start(
+        len.saturating_sub(newline.len() as u64),
+    ))?;
+    file.read_exact(&mut end).or_else(|e| match e.kind() {
+        // A file shorter than a newline can't end with one
+        io::ErrorKind::UnexpectedEof => Ok(()),
+        _ => Err(e),
+    })?;
+    if end != newline {
+        file.write_all(newline)?;
+    }
+    file.write_all(&report[bom..])
+}
+
+/// The name `filename` is reported under in messages: standard input read
+/// without being named has an empty one.
+fn display_name(filename: &str) -> &str {
+    if filename.is_empty() {
+        "standard input"
+    } else {
+        filename
+    }
+}
+
+/// What failed the checks, or is only worth a warning, as the messages for
+/// stderr and whether each one fails the run.
+fn problems(results: &[Result<WordCount, CountError>], args: &Args) -> Vec<(String, bool)> {
+    let mut problems = Vec::new();
+    for wc in results.iter().flatten() {
+        let name = display_name(&wc.filename);
+        if let Some(error) = wc.xml.as_ref().and_then(|xml| xml.error.as_ref()) {
+            problems.push((format!("wc: {}: malformed XML {}", name, error), false));
+        }
+        if let Some(error) = wc.json.as_ref().and_then(|json| json.error.as_ref()) {
+            problems.push((format!("wc: {}: invalid JSON {}", name, error), true));
+        }
+        if let Some(line) = wc.first_tab {
+            problems.push((format!("wc: {}:{}: line contains a tab", name, line), true));
+        }
+        if (args.warn_no_newline || args.fail_no_newline) && wc.missing_newline {
+            let message = format!("wc: {}: no newline at end of file", name);
+            problems.push((message, args.fail_no_newline));
+        }
+        if args.fail_on_mixed_endings && wc.line_endings.as_ref().is_some_and(|le| le.mixed()) {
+            problems.push((format!("wc: {}: mixed line endings", name), true));
+        }
+        if let Some(line) = wc.ascii.as_ref().and_then(|ascii| ascii.first_line) {
+            if args.fail_non_ascii {
+                problems.push((format!("wc: {}:{}: line isn't ASCII", name, line), true));
+            }
+        }
+        if args.require_bom && wc.bom.is_none() {
+            problems.push((format!("wc: {}: missing byte order mark", name), true));
+        }
+        if args.forbid_bom && wc.bom.is_some() {
+            problems.push((format!("wc: {}: has a byte order mark", name), true));
+        }
+    }
+    problems
+}
+
+/// The exit status: 1 if an input couldn't be counted or failed a check, and
+/// 0 otherwise.
+fn status(results: &[Result<WordCount, CountError>], total: &WordCount, args: &Args) -> i32 {
+    let failed = args.names_failed
+        || results.iter().any(Result::is_err)
+        || args.exit_if.as_ref().is_some_and(|c| c.holds(total))
+        || problems(results, args).iter().any(|&(_, fails)| fails);
+    failed as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a command line, without the program name.
+    fn args(args: &[&str]) -> Args {
+        Args::parse(args.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    /// Writes `contents` to a file of its own in the temp directory.
+    fn temp_file(name: &str, contents: &[u8]) -> String {
+        let dir = std::env::temp_dir().join(format!("wc-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    /// The report `wc` writes to stdout for `args`.
+    fn output(args: &Args) -> Vec<u8> {
+        let results = count(args);
+        let total = total(results.iter().flatten());
+        report(&results, &total, args)
+    }
+
+    fn text_output(args: &Args) -> String {
+        String::from_utf8(output(args)).unwrap()
+    }
+
+    #[test]
+    fn numeric_total_has_no_label() {
+        let a = temp_file("numeric-total-a", b"one two\n");
+        let b = temp_file("numeric-total-b", b"three\n");
+        let out = text_output(&args(&["--numeric-total", &a, &b]));
+        assert_eq!(out, format!(" 1  2  8 {}\n 1  1  6 {}\n 2  3 14\n", a, b));
+    }
+
+    #[test]
+    fn total_only_is_just_the_numbers() {
+        let a = temp_file("total-only-a", b"one two\n");
+        let b = temp_file("total-only-b", b"three\n");
+        let out = text_output(&args(&["--total=only", &a, &b]));
+        assert_eq!(out, " 2  3 14\n");
+    }
+
+    /// Blocks until its sender is dropped, as a pipe with a stalled writer would.
+    struct Stalled(Receiver<()>);
+
+    impl Read for Stalled {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            let _ = self.0.recv();
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn read_timeout_fails_a_stalled_input() {
+        let args = args(&["--read-timeout=0.1"]);
+        let (_writer, stalled) = mpsc::channel();
+        let e = count_input(String::new(), Stalled(stalled), None, None, &args).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(e.to_string(), "no data received within 0.1 seconds");
+
+        let results = vec![Err(io_error("standard input", &e))];
+        let total = total(results.iter().flatten());
+        assert_eq!(
+            results[0].as_ref().unwrap_err().to_string(),
+            "wc: standard input: no data received within 0.1 seconds"
+        );
+        assert_eq!(status(&results, &total, &args), 1);
+    }
+
+    #[test]
+    fn totals_pass_the_32_bit_boundary() {
+        let a = temp_file("wide-a", b"a\n");
+        let b = temp_file("wide-b", b"b\n");
+        let args = args(&["-c", &a, &b]);
+        let mut results = count(&args);
+        results[0].as_mut().unwrap().bytes = u32::MAX as u64;
+        // A 32-bit count would wrap around to 1 here
+        let total = total(results.iter().flatten());
+        assert_eq!(total.bytes, u32::MAX as u64 + 2);
+
+        // Every row is padded to the width of the total
+        let out = String::from_utf8(report(&results, &total, &args)).unwrap();
+        assert_eq!(
+            out,
+            format!("4294967295 {}\n         2 {}\n4294967297 total\n", a, b)
+        );
+    }
+
+    /// A writer that can still be read after it's been handed over.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn progress_records_follow_the_count() {
+        let files = [
+            temp_file("progress-1", b"one\n"),
+            temp_file("progress-2", b"two two\n"),
+            temp_file("progress-3", b"three three three\n"),
+        ];
+        let mut argv = vec![
+            "--progress-json",
+            "--progress-interval=0.000001",
+            "--jobs=1",
+        ];
+        argv.extend(files.iter().map(String::as_str));
+        let written = Shared::default();
+        let results = count_reporting_to(&args(&argv), Box::new(written.clone()));
+        assert!(results.iter().all(Result::is_ok));
+
+        let written = String::from_utf8(written.0.lock().unwrap().clone()).unwrap();
+        let records: Vec<Vec<(&str, &str)>> = written
+            .lines()
+            .map(|record| {
+                let json = JsonReport::parse(record);
+                assert_eq!(json.error, None, "{}", record);
+                assert_eq!((json.objects, json.numbers, json.nulls), (1, 3, 0));
+                record
+                    .trim_start_matches('{')
+                    .trim_end_matches('}')
+                    .split(',')
+                    .map(|field| field.split_once(':').unwrap())
+                    .collect()
+            })
+            .collect();
+        assert!(!records.is_empty());
+        let field = |record: &[(&str, &str)], name: &str| -> u64 {
+            let (_, value) = record
+                .iter()
+                .find(|(key, _)| key.trim_matches('"') == name)
+                .unwrap();
+            value.parse().unwrap()
+        };
+        for pair in records.windows(2) {
+            assert!(field(&pair[0], "bytes_done") <= field(&pair[1], "bytes_done"));
+            assert!(field(&pair[0], "files_done") <= field(&pair[1], "files_done"));
+        }
+        let last = records.last().unwrap();
+        assert_eq!(field(last, "bytes_done"), 30);
+        assert_eq!(field(last, "bytes_total"), 30);
+        assert_eq!(field(last, "files_done"), 3);
+    }
+
+    #[test]
+    fn pre_sized_reads_match_growing_ones() {
+        let contents: Vec<u8> = (0..100_000u32).flat_map(|i| i.to_le_bytes()).collect();
+        let path = temp_file("pre-sized", &contents);
+        // The timeout wrapper hides the file's size from `read_to_end`
+        let args = args(&["--read-timeout=10", &path]);
+
+        let size = std::fs::metadata(&path).unwrap().len();
+        let pre_sized = read_input(File::open(&path).unwrap(), Some(size), None, &args).unwrap();
+        let growing = read_input(File::open(&path).unwrap(), None, None, &args).unwrap();
+        assert_eq!(pre_sized, contents);
+        assert_eq!(growing, contents);
+        // Reading into the exact size never has to grow the buffer
+        assert_eq!(pre_sized.capacity(), contents.len());
+    }
+
+    /// The bytes, words and lines of every row for `args`.
+    fn counts(args: &Args) -> Vec<(u64, u64, u64)> {
+        count(args)
+            .iter()
+            .map(|wc| {
+                let wc = wc.as_ref().unwrap();
+                (wc.bytes, wc.words, wc.lines)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merge_sums_inputs_split_between_words() {
+        let a = temp_file("merge-between-a", b"one two \n");
+        let b = temp_file("merge-between-b", b"three four\n");
+        let per_file = counts(&args(&[&a, &b]));
+        let summed = per_file.iter().fold((0, 0, 0), |(c, w, l), &(fc, fw, fl)| {
+            (c + fc, w + fw, l + fl)
+        });
+        assert_eq!(counts(&args(&["--merge", &a, &b])), [summed]);
+        assert_eq!(summed, (20, 4, 2));
+    }
+
+    #[test]
+    fn merge_joins_a_word_split_across_inputs() {
+        let a = temp_file("merge-within-a", b"hello wor");
+        let b = temp_file("merge-within-b", b"ld\n");
+        let joined = temp_file("merge-within-joined", b"hello world\n");
+        assert_eq!(counts(&args(&[&a, &b])), [(9, 2, 1), (3, 1, 1)]);
+        assert_eq!(
+            counts(&args(&["--merge", &a, &b])),
+            counts(&args(&[&joined]))
+        );
+        assert_eq!(counts(&args(&[&joined])), [(12, 2, 1)]);
+    }
+
+    #[test]
+    fn utf16le_output_starts_with_a_bom() {
+        let path = temp_file("utf16le", b"a b\n");
+        let out = output(&args(&["--output-encoding=utf16le", "-lw", &path]));
+        assert_eq!(out[..2], [0xFF, 0xFE]);
+
+        let mut row = vec![b'1', 0, b' ', 0, b'2', 0, b' ', 0];
+        row.extend(path.bytes().flat_map(|b| [b, 0]));
+        row.extend([b'\n', 0]);
+        assert_eq!(out[2..], row);
+    }
+
+    #[test]
+    fn brackets_report_the_first_unmatched() {
+        let path = temp_file("brackets", b"(a[b)]\n");
+        let out = text_output(&args(&["--brackets", &path]));
+        assert_eq!(
+            out,
+            format!(
+                "1 1 7 {0}\n{0}: brackets () 1/1 [] 1/1 {{}} 0/0, unmatched ')' at byte 4\n",
+                path
+            )
+        );
+    }
+
+    #[test]
+    fn markdown_counts_prose_words() {
+        let path = temp_file(
+            "markdown",
+            b"# Title\n\nSome *bold* [link](http://x.y) text.\n\n```\ncode here\n```\n",
+        );
+        assert_eq!(text_output(&args(&["-w", &path])), format!("10 {}\n", path));
+        assert_eq!(
+            text_output(&args(&["--markdown", "-w", &path])),
+            format!("7 {}\n", path)
+        );
+    }
+
+    #[test]
+    fn html_counts_visible_text() {
+        let path = temp_file(
+            "html",
+            b"<p>Hello <b>big</b> world</p><script>var x = 1;</script>\n",
+        );
+        assert_eq!(
+            text_output(&args(&["--html", "-w", &path])),
+            format!("3 {}\n", path)
+        );
+    }
+
+    #[test]
+    fn columns_count_each_range() {
+        let path = temp_file("columns", b"abcdef ghij\nxy z\n");
+        assert_eq!(
+            text_output(&args(&["--columns=1-3,5-8", &path])),
+            format!(
+                " 2  4 17 {0}\n{0}: columns 1-3 chars 6 words 2\n{0}: columns 5-8 chars 4 words 2\n",
+                path
+            )
+        );
+    }
+
+    #[test]
+    fn todo_counts_and_lists_markers() {
+        let path = temp_file("todo", b"// TODO: one\nx // FIXME two\n/* XXX */ TODO\n");
+        assert_eq!(
+            text_output(&args(&["--todo", "--list", &path])),
+            format!(
+                " 3 11 43 {0}\n{0}: TODO 2 FIXME 1 XXX 1 HACK 0\n\
+                 {0}:1: TODO\n{0}:2: FIXME\n{0}:3: XXX\n{0}:3: TODO\n",
+                path
+            )
+        );
+    }
+
+    #[test]
+    fn emoji_counts_grapheme_clusters() {
+        // A skin tone, a flag and a family are one emoji each
+        let path = temp_file("emoji", "hi 👋🏽 🇫🇷 ok 👨‍👩‍👧\n".as_bytes());
+        assert_eq!(
+            text_output(&args(&["--emoji", &path])),
+            format!("3 {}\n", path)
+        );
+    }
+
+    #[test]
+    fn between_counts_only_sections() {
+        let path = temp_file("between", b"a b\nBEGIN\nc d e\nEND\nf\nBEGIN\ng\nEND\n");
+        assert_eq!(
+            text_output(&args(&["--between=^BEGIN$:^END$", &path])),
+            format!("2 4 8 {}\n", path)
+        );
+    }
+
+    #[test]
+    fn empty_input_counts_as_one_line() {
+        let path = temp_file("empty-as-one-line", b"");
+        assert_eq!(text_output(&args(&[&path])), format!("0 0 0 {}\n", path));
+        assert_eq!(
+            text_output(&args(&["--empty-as-one-line", &path])),
+            format!("1 0 0 {}\n", path)
+        );
+    }
+
+    #[test]
+    fn urls_count_and_list_distinct() {
+        let path = temp_file(
+            "urls",
+            b"see https://a.com/x and http://b.org, again https://a.com/x.\nftp://no\n",
+        );
+        assert_eq!(
+            text_output(&args(&["--urls", "--list", &path])),
+            format!(
+                " 2  7 70 {0}\n{0}: urls 3 distinct 2\n{0}: http://b.org\n{0}: https://a.com/x\n",
+                path
+            )
+        );
+    }
+
+    #[test]
+    fn exit_if_fails_past_the_threshold() {
+        let path = temp_file("exit-if", b"one\ntwo\nthree\n");
+        for (condition, expected) in [("lines>2", 1), ("lines>=4", 0), ("words==3", 1)] {
+            let args = args(&[&format!("--exit-if={}", condition), &path]);
+            let results = count(&args);
+            let total = total(results.iter().flatten());
+            assert_eq!(status(&results, &total, &args), expected, "{}", condition);
+        }
+    }
+
+    #[test]
+    fn code_blocks_count_fenced_and_indented() {
+        let path = temp_file(
+            "code-blocks",
+            b"text\n\n```rust\nfn a() {}\nfn b() {}\n```\n\n    indented\n\nmore\n",
+        );
+        assert_eq!(
+            text_output(&args(&["--code-blocks", &path])),
+            format!(
+                "10 11 58 {0}\n{0}: code blocks 2 (fenced 1, indented 1) lines 3\n",
+                path
+            )
+        );
+    }
+
+    #[test]
+    fn utf8_stats_count_by_length() {
+        let path = temp_file("utf8-stats", "aé€😀\n".as_bytes());
+        assert_eq!(
+            text_output(&args(&["--utf8-stats", &path])),
+            format!(
+                " 1  1 11 {0}\n{0}: utf8 1-byte 2 2-byte 1 3-byte 1 4-byte 1\n",
+                path
+            )
+        );
+    }
+
+    #[test]
+    fn whitespace_counts_every_kind() {
+        let path = temp_file("whitespace", b"a \t b\n  c\n");
+        assert_eq!(
+            text_output(&args(&["--whitespace", &path])),
+            format!("7 {}\n", path)
+        );
+    }
+
+    #[test]
+    fn only_filters_rows_not_the_total() {
+        let kept = temp_file("only.txt", b"one two\n");
+        let dropped = temp_file("only.md", b"three\n");
+        assert_eq!(
+            text_output(&args(&["--only=*.txt", &kept, &dropped])),
+            format!(" 1  2  8 {}\n 2  3 14 total\n", kept)
+        );
+    }
+
+    #[test]
+    fn repeated_lines_find_the_most_repeated() {
+        let path = temp_file("repeated-lines", b"a\nb\na\nc\na\nb\n");
+        assert_eq!(
+            text_output(&args(&["--repeated-lines", &path])),
+            format!(
+                " 6  6 12 {0}\n{0}: repeated lines 2, most repeated 3x \"a\"\n",
+                path
+            )
+        );
+        assert_eq!(
+            text_output(&args(&["--repeated-lines", "--cap-repeat-memory=1", &path])),
+            format!(
+                " 6  6 12 {0}\n{0}: repeated lines 1 (capped), most repeated 3x \"a\"\n",
+                path
+            )
+        );
+    }
+
+    #[test]
+    fn bom_marked_input_is_decoded() {
+        let utf16 = temp_file("auto-decode", b"\xff\xfea\x00 \x00b\x00\n\x00");
+        assert_eq!(
+            Encoding::detect(&std::fs::read(&utf16).unwrap()),
+            (Encoding::Utf16Le, 2)
+        );
+        assert_eq!(
+            text_output(&args(&[&utf16])),
+            format!(" 1  2 10 {}\n", utf16)
+        );
+        // Read as UTF-8, the NUL after the newline is a line and word of its own
+        assert_eq!(
+            text_output(&args(&["--no-detect", &utf16])),
+            format!(" 2  3 10 {}\n", utf16)
+        );
+        assert_eq!(
+            text_output(&args(&["--no-detect", "--auto-decode", &utf16])),
+            format!(" 1  2 10 {}\n", utf16)
+        );
+
+        let utf8 = temp_file("auto-decode-utf8", b"\xef\xbb\xbfa b\n");
+        assert_eq!(text_output(&args(&["-m", &utf8])), format!("4 {}\n", utf8));
+        assert_eq!(
+            text_output(&args(&["-m", "--no-detect", &utf8])),
+            format!("5 {}\n", utf8)
+        );
+    }
+
+    #[test]
+    fn strip_comments_keeps_strings() {
+        let rust = temp_file(
+            "strip-comments.rs",
+            b"let a = 1; // note\n// whole line\n/* block\n more */ let b = \"//no\";\n",
+        );
+        assert_eq!(
+            text_output(&args(&["--strip-comments=rust", &rust])),
+            format!(" 2  8 29 {}\n", rust)
+        );
+        let python = temp_file("strip-comments.py", b"x = 1  # c\n# only\ny = \"#no\"\n");
+        assert_eq!(
+            text_output(&args(&["--strip-comments=python", &python])),
+            format!(" 2  6 18 {}\n", python)
+        );
+    }
+
+    #[test]
+    fn first_chars_group_lowercased() {
+        let path = temp_file("first-chars", b"Apple apricot banana Berry cherry\n");
+        assert_eq!(
+            text_output(&args(&["--first-chars", &path])),
+            format!(" 1  5 34 {0}\n{0}: first characters a 2 b 2 c 1\n", path)
+        );
+    }
+
+    #[test]
+    fn advanced_sentences_join_before_lowercase() {
+        let path = temp_file("sentences-advanced", b"Wait... what? yes.\n");
+        assert_eq!(
+            text_output(&args(&["--sentences", &path])),
+            format!("2 {}\n", path)
+        );
+        assert_eq!(
+            text_output(&args(&["--sentences-advanced", &path])),
+            format!("1 {}\n", path)
+        );
+
+        let text = temp_file("abbrev-text", b"It took zork. Ten days.\n");
+        let abbreviations = temp_file("abbrev-file", b"zork\n");
+        assert_eq!(
+            text_output(&args(&["--sentences-advanced", &text])),
+            format!("2 {}\n", text)
+        );
+        assert_eq!(
+            text_output(&args(&[
+                "--sentences-advanced",
+                &format!("--abbrev-file={}", abbreviations),
+                &text
+            ])),
+            format!("1 {}\n", text)
+        );
+    }
+
+    #[test]
+    fn char_ranges_count_each_range() {
+        let path = temp_file("char-range", "abc ÄÖ 日本 Ab\n".as_bytes());
+        assert_eq!(
+            text_output(&args(&["--char-range=0041-005A,U+3040-U+9FFF", &path])),
+            format!(
+                " 1  4 19 {0}\n{0}: chars U+0041-U+005A 1\n{0}: chars U+3040-U+9FFF 2\n",
+                path
+            )
+        );
+    }
+
+    #[test]
+    fn fail_on_tabs_finds_the_first_tab() {
+        let path = temp_file("fail-on-tabs", b"a\tb\n\tc\n");
+        for (options, line) in [
+            (&["--fail-on-tabs"][..], 1),
+            (&["--fail-on-tabs", "--leading-only"], 2),
+        ] {
+            let args = args(&[options, &[path.as_str()]].concat());
+            let results = count(&args);
+            assert_eq!(results[0].as_ref().unwrap().first_tab, Some(line));
+            let total = total(results.iter().flatten());
+            assert_eq!(status(&results, &total, &args), 1);
+        }
+
+        let args = args(&[
+            "--fail-on-tabs",
+            "--leading-only",
+            &temp_file("no-leading-tabs", b"a\tb\n"),
+        ]);
+        let results = count(&args);
+        let total = total(results.iter().flatten());
+        assert_eq!(status(&results, &total, &args), 0);
+    }
+
+    #[test]
+    fn distinct_bytes_counts_byte_values() {
+        let path = temp_file("distinct-bytes", b"abcabc\n");
+        assert_eq!(
+            text_output(&args(&["--distinct-bytes", &path])),
+            format!("4 {}\n", path)
+        );
+    }
+
+    #[test]
+    fn bom_policies() {
+        let bom = temp_file("check-bom", b"\xef\xbb\xbfa b\n");
+        let plain = temp_file("check-bom-plain", b"abcabc\n");
+        assert_eq!(
+            text_output(&args(&["--check-bom", &bom, &plain])),
+            format!(
+                " 1  2  7 {0}\n{0}: UTF-8 BOM\n 1  1  7 {1}\n{1}: no BOM\n 2  3 14 total\n",
+                bom, plain
+            )
+        );
+
+        for (policy, file, expected) in [
+            ("--require-bom", &bom, 0),
+            ("--require-bom", &plain, 1),
+            ("--forbid-bom", &bom, 1),
+            ("--forbid-bom", &plain, 0),
+        ] {
+            let args = args(&[policy, file]);
+            let results = count(&args);
+            let total = total(results.iter().flatten());
+            assert_eq!(
+                status(&results, &total, &args),
+                expected,
+                "{} {}",
+                policy,
+                file
+            );
+        }
+    }
+
+    #[test]
+    fn words_no_numbers_skips_numbers() {
+        let path = temp_file("words-no-numbers", b"one 2 3.5 -4 x1 1st\n");
+        assert_eq!(
+            text_output(&args(&["--words-no-numbers", &path])),
+            format!("3 {}\n", path)
+        );
+    }
+
+    #[test]
+    fn continuation_lines_join_logical_lines() {
+        let path = temp_file("continuation-lines", b"a \\\n b\nc\n");
+        assert_eq!(
+            text_output(&args(&["--count-continuation-lines", &path])),
+            format!("3 4 9 {0}\n{0}: physical lines 3 logical lines 2\n", path)
+        );
+    }
+
+    #[test]
+    fn bytes_no_eol_leaves_out_line_endings() {
+        let path = temp_file("bytes-no-eol", b"ab\r\ncd\n");
+        assert_eq!(
+            text_output(&args(&["--bytes-no-eol", &path])),
+            format!("4 {}\n", path)
+        );
+    }
+
+    #[test]
+    fn percent_shows_shares_of_the_total() {
+        let a = temp_file("percent-a", b"a\nb\nc\n");
+        let b = temp_file("percent-b", b"d\n");
+        assert_eq!(
+            text_output(&args(&["--percent", "-l", &a, &b])),
+            format!(
+                "3 {0}\n{0}: of total lines 75.0%\n1 {1}\n{1}: of total lines 25.0%\n4 total\n",
+                a, b
+            )
+        );
+    }
+
+    /// Hands out its input a byte at a time, so every token spans reads.
+    struct Trickle(io::Cursor<Vec<u8>>);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(1);
+            self.0.read(&mut buf[..n])
+        }
+    }
+
+    #[test]
+    fn xml_is_counted_as_it_streams() {
+        let args = args(&["--xml"]);
+        assert!(args.streams());
+        let xml = b"<a x=\"1\"><b/><b y=\"2\" z=\"3\">t</b></a>\n";
+        let reader = Trickle(io::Cursor::new(xml.to_vec()));
+        let wc = count_input(String::new(), reader, None, None, &args).unwrap();
+        assert_eq!((wc.lines, wc.words, wc.bytes), (1, 4, 38));
+
+        let report = wc.xml.unwrap();
+        assert_eq!(
+            report.elements,
+            BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+        );
+        assert_eq!((report.attributes, report.error), (3, None));
+    }
+
+    #[test]
+    fn malformed_xml_is_still_counted_to_the_end() {
+        let args = args(&["--xml"]);
+        let reader = Trickle(io::Cursor::new(b"<a><b></a>\nafter the error\n".to_vec()));
+        let wc = count_input(String::new(), reader, None, None, &args).unwrap();
+        assert_eq!((wc.lines, wc.words, wc.bytes), (2, 4, 27));
+        assert_eq!(
+            wc.xml.unwrap().error.unwrap(),
+            "at byte 6: ill-formed document: expected `</b>`, but `</a>` was found"
+        );
+    }
+
+    #[test]
+    fn json_is_counted_the_same_at_every_split() {
+        let json = r#"{"a": [1, -2.5e+3, true, null, "xé\n"], "b": {}}"#;
+        let whole = JsonReport::parse(json);
+        assert_eq!(
+            (whole.objects, whole.arrays, whole.strings, whole.numbers),
+            (2, 1, 1, 2)
+        );
+        assert_eq!((whole.booleans, whole.nulls, whole.error), (1, 1, None));
+
+        // Splits fall inside tokens, and inside the UTF-8 sequence of é
+        let json = json.as_bytes();
+        for split in 0..json.len() {
+            let mut scanner = JsonScanner::default();
+            scanner.update(&json[..split]);
+            scanner.update(&json[split..]);
+            let report = scanner.finish();
+            assert_eq!(
+                (report.objects, report.strings, report.numbers, report.error),
+                (2, 1, 2, None),
+                "split at {}",
+                split
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_json_is_reported_across_reads() {
+        let args = args(&["--json-stats"]);
+        assert!(args.streams());
+        let reader = Trickle(io::Cursor::new(br#"[1, {"a" 2}]"#.to_vec()));
+        let wc = count_input(String::new(), reader, None, None, &args).unwrap();
+        let report = wc.json.unwrap();
+        assert_eq!((report.arrays, report.objects, report.numbers), (1, 1, 1));
+        assert_eq!(report.error.unwrap(), "at byte 9: expected ':'");
+
+        let reader = Trickle(io::Cursor::new(br#"{"a": "\u12"#.to_vec()));
+        let wc = count_input(String::new(), reader, None, None, &args).unwrap();
+        assert_eq!(
+            wc.json.unwrap().error.unwrap(),
+            "at byte 8: expected an escape sequence"
+        );
+    }
+
+    #[test]
+    fn empty_walk_counts_nothing() {
+        let dir = std::env::temp_dir().join(format!("wc-test-{}-empty-walk", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        let dir = dir.to_string_lossy().into_owned();
+
+        let walk = args(&["-r", &dir]);
+        assert!(walk.files.is_empty());
+        assert!(count(&walk).is_empty());
+        assert_eq!(text_output(&walk), "");
+        assert_eq!(
+            text_output(&args(&["-r", "--total=always", &dir])),
+            "0 0 0 total\n"
+        );
+    }
+
+    #[test]
+    fn long_option_values_can_follow() {
+        let path = temp_file("option-values", b"one two\n");
+        for (joined, separate) in [
+            (&["--format=json"][..], &["--format", "json"][..]),
+            (&["--total=only"], &["--total", "only"]),
+            (
+                &["--only=*", "--sort=words"],
+                &["--only", "*", "--sort", "words"],
+            ),
+        ] {
+            assert_eq!(
+                text_output(&args(&[joined, &[path.as_str()]].concat())),
+                text_output(&args(&[separate, &[path.as_str()]].concat())),
+                "{:?}",
+                separate
+            );
+        }
+        assert_eq!(
+            text_output(&args(&["--format", "json", &path])),
+            format!(
+                "[\n  {{\"filename\": \"{}\", \"lines\": 1, \"words\": 2, \"bytes\": 8}}\n]\n",
+                path
+            )
+        );
+        // The value may start with '-'
+        assert_eq!(args(&["--only", "-*", &path]).files, [path]);
+    }
+
+    #[test]
+    fn jobs_value_can_follow() {
+        let a = temp_file("jobs-a", b"one two\n");
+        let b = temp_file("jobs-b", b"three\n");
+        assert_eq!(args(&["--jobs", "3", &a, &b]).jobs, 3);
+        assert_eq!(
+            text_output(&args(&["--jobs", "2", &a, &b])),
+            format!(" 1  2  8 {}\n 1  1  6 {}\n 2  3 14 total\n", a, b)
+        );
+    }
+
+    #[test]
+    fn encoding_name_can_follow() {
+        let path = temp_file("encoding-latin1", b"caf\xe9\n");
+        assert_eq!(
+            text_output(&args(&["-m", "--encoding", "latin1", &path])),
+            format!("5 {}\n", path)
+        );
+        assert_eq!(
+            text_output(&args(&["-m", "--encoding=latin1", &path])),
+            format!("5 {}\n", path)
+        );
+    }
+
+    #[test]
+    fn top_value_can_follow() {
+        let path = temp_file("top", b"a b a c a b\n");
+        assert_eq!(
+            text_output(&args(&["--frequency", "--top", "2", &path])),
+            format!(
+                " 1  6 12 {0}\n{0}: frequency 3 a\n{0}: frequency 2 b\n",
+                path
+            )
+        );
+    }
+
+    #[test]
+    fn histogram_kind_can_follow() {
+        let path = temp_file("histogram", b"ab\nabcd\nabcdef\n");
+        let separate = text_output(&args(&[
+            "--histogram",
+            "line-length",
+            "--buckets",
+            "2",
+            &path,
+        ]));
+        assert_eq!(
+            separate,
+            text_output(&args(&["--histogram=line-length", "--buckets=2", &path]))
+        );
+        assert!(separate.contains(&format!("{}: line length 0-2 1 ", path)));
+    }
+
+    #[test]
+    fn decimals_use_the_separator() {
+        assert_eq!(decimal(std::f64::consts::PI, 2, '.'), "3.14");
+        assert_eq!(decimal(std::f64::consts::PI, 2, ','), "3,14");
+        assert_eq!(decimal(-0.25, 1, ','), "-0,2");
+        assert_eq!(binary_size(1536, ','), "1,5 KiB");
+
+        let path = temp_file("decimal-sep", b"ab\nabcd\n");
+        let out = text_output(&args(&["--stats", "--decimal-sep", ",", &path]));
+        assert_eq!(
+            out,
+            format!(
+                "2 2 8 {0}\n{0}: line length min 2 max 4 mean 3,0 median 3,0\n\
+                 {0}: word length min 2 max 4 mean 3,0 median 3,0\n",
+                path
+            )
+        );
+        assert!(text_output(&args(&["--stats", "--decimal-sep=.", &path])).contains("mean 3.0"));
+    }
+
+    #[test]
+    fn sorted_json_is_the_same_every_run() {
+        let b = temp_file("sorted-json-b", b"three\n");
+        let a = temp_file("sorted-json-a", b"one two\n");
+        let args = args(&[
+            "--format=json",
+            "--sort-json-keys",
+            "--sort=name",
+            "--jobs=4",
+            &b,
+            &a,
+        ]);
+        let first = output(&args);
+        let second = output(&args);
+        assert_eq!(first, second);
+        assert_eq!(
+            String::from_utf8(first).unwrap(),
+            format!(
+                "[\n  {{\"bytes\": 8, \"filename\": \"{}\", \"lines\": 1, \"words\": 2}},\n  \
+                 {{\"bytes\": 6, \"filename\": \"{}\", \"lines\": 1, \"words\": 1}},\n  \
+                 {{\"bytes\": 14, \"lines\": 2, \"type\": \"total\", \"words\": 3}}\n]\n",
+                a, b
+            )
+        );
+    }
+
+    #[test]
+    fn parallel_counts_move_progress_on() {
+        let files = [
+            temp_file("parallel-progress-1", b"one\n"),
+            temp_file("parallel-progress-2", b"two two\n"),
+            temp_file("parallel-progress-3", b"three three three\n"),
+        ];
+        let mut args = args(&["--jobs=2"]);
+        args.files = files.to_vec();
+        let mut progress = Progress::new(Some(30), Duration::from_secs(3600));
+
+        let results = count_parallel(&args.files, args.jobs, Some(&mut progress), &args);
+        let counted: Vec<u64> = results
+            .iter()
+            .map(|wc| wc.as_ref().unwrap().words)
+            .collect();
+        assert_eq!(counted, [1, 2, 3]);
+        assert_eq!((progress.bytes_done, progress.files_done), (30, 3));
+    }
+
+    #[test]
+    fn merge_is_the_total_row() {
+        let a = temp_file("merge-total-a", b"a b\n");
+        let b = temp_file("merge-total-b", b"c\n");
+        assert_eq!(text_output(&args(&["--merge", &a, &b])), "2 3 6 total\n");
+        assert_eq!(
+            text_output(&args(&["--merge", "--total=only", &a, &b])),
+            "2 3 6\n"
+        );
+        assert_eq!(
+            text_output(&args(&["--merge", "--total=never", &a, &b])),
+            ""
+        );
+    }
+
+    #[test]
+    fn files0_skips_names_that_cannot_be_counted() {
+        let list = temp_file("files0-bad-names", b"a\0\0b\0\xff\0c");
+        let (files, failed) = read_files0(&list);
+        assert_eq!(files, ["a", "b", "c"]);
+        assert!(failed);
+
+        let list = temp_file("files0-good-names", b"a\0b\0");
+        assert_eq!(
+            read_files0(&list),
+            (vec!["a".to_string(), "b".to_string()], false)
+        );
+    }
+
+    #[test]
+    fn byte_order_marks_are_found_across_reads() {
+        let args = args(&["-lwmc"]);
+        let utf16 = b"\xff\xfea\x00 \x00b\x00\n\x00".to_vec();
+        let reader = Trickle(io::Cursor::new(utf16.clone()));
+        let wc = count_input(String::new(), reader, None, None, &args).unwrap();
+        let whole = count_bytes(String::new(), &utf16, None, &args).unwrap();
+        assert_eq!((wc.lines, wc.words, wc.chars, wc.bytes), (1, 2, 4, 10));
+        assert_eq!(
+            (wc.lines, wc.words, wc.chars),
+            (whole.lines, whole.words, whole.chars)
+        );
+
+        let reader = Trickle(io::Cursor::new(b"\xef\xbb\xbfab\n".to_vec()));
+        let wc = count_input(String::new(), reader, None, None, &args).unwrap();
+        assert_eq!((wc.chars, wc.bytes), (3, 6));
+    }
+
+    #[test]
+    fn running_counts_do_not_wait_for_a_byte_order_mark() {
+        let args = args(&[]);
+        let mut counter = Counter::for_args(&args);
+        counter.update(b"a").unwrap();
+        let running = counter.clone().finish(String::new(), &args).unwrap();
+        assert_eq!((running.words, running.bytes), (1, 1));
+    }
+
+    #[test]
+    fn problems_name_unnamed_standard_input() {
+        let args = args(&["--xml", "--fail-on-tabs"]);
+        let wc = count_bytes(String::new(), b"<a>\tb</b>\n", None, &args).unwrap();
+        let results = vec![Ok(wc)];
+        let messages: Vec<String> = problems(&results, &args)
+            .into_iter()
+            .map(|(message, _)| message)
+            .collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].starts_with("wc: standard input: malformed XML"));
+        assert_eq!(messages[1], "wc: standard input:1: line contains a tab");
+    }
+
+    #[test]
+    fn json_names_errors_and_marks_the_total() {
+        let dir = std::env::temp_dir().join(format!("wc-test-{}", std::process::id()));
+        let named_total = temp_file("total", b"one two\n");
+        let missing = dir.join("json-missing").to_string_lossy().into_owned();
+        let out = text_output(&args(&["--format=json", "-w", &named_total, &missing]));
+        assert_eq!(
+            out,
+            format!(
+                "[\n  {{\"filename\": \"{}\", \"words\": 2}},\n  \
+                 {{\"filename\": \"{}\", \"error\": \"No such file or directory\"}},\n  \
+                 {{\"type\": \"total\", \"words\": 2}}\n]\n",
+                named_total, missing
+            )
+        );
+    }
+
+    #[test]
+    fn named_encodings_leave_out_their_byte_order_mark() {
+        let utf16 = temp_file("encoding-bom-utf16", b"\xff\xfea\x00b\x00");
+        let utf8 = temp_file("encoding-bom-utf8", b"\xef\xbb\xbfab");
+        let chars = |flags: &[&str], path: &str| {
+            let mut argv = vec!["-m"];
+            argv.extend(flags);
+            argv.push(path);
+            output(&args(&argv))
+        };
+        assert_eq!(
+            chars(&["--encoding=utf-16le"], &utf16),
+            format!("2 {}\n", utf16).into_bytes()
+        );
+        assert_eq!(
+            chars(&["--encoding=utf-8"], &utf8),
+            format!("2 {}\n", utf8).into_bytes()
+        );
+        assert_eq!(
+            chars(&["--encoding=utf-16le", "--no-detect"], &utf16),
+            format!("3 {}\n", utf16).into_bytes()
+        );
+    }
+
+    #[test]
+    fn html_separates_words_without_counting_it() {
+        let prose = html_text("<p>hello</p>");
+        assert_eq!((prose.text.as_str(), prose.separators), ("hello", 0));
+
+        let prose = html_text("<p>a</p><p>b</p>&eacute;&hellip;&NoSuch;<SCRIPT>x</Script>c");
+        assert_eq!(prose.text, "a\nb\né…&NoSuch;c");
+        assert_eq!(prose.separators, 2);
+
+        let path = temp_file("html-chars.html", b"<p>hello</p><div>world</div>");
+        assert_eq!(
+            text_output(&args(&["--html", "-wm", &path])),
+            format!(" 2 10 {}\n", path)
+        );
+    }
+
+    #[test]
+    fn html_entity_table_is_in_name_order() {
+        assert!(HTML_ENTITIES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn markdown_separates_blocks_without_counting_it() {
+        let prose = markdown_text("*hello*", false);
+        assert_eq!((prose.text.as_str(), prose.separators), ("hello", 0));
+
+        let prose = markdown_text("# Title\n\npara one\nline two\n\n- item\n", false);
+        assert_eq!(prose.text, "Title\npara one\nline two\nitem");
+        assert_eq!(prose.separators, 2);
+
+        let path = temp_file("markdown-chars.md", b"*hello*");
+        assert_eq!(
+            text_output(&args(&["--markdown", "-lwm", &path])),
+            format!("1 1 5 {}\n", path)
+        );
+    }
+
+    #[test]
+    fn append_accumulates_runs_in_the_output_file() {
+        let input = temp_file("append-input", b"one two\n");
+        let report = temp_file("append-report", b"");
+        let appending = args(&["--output", &report, "--append", &input]);
+        assert_eq!(run(&appending), 0);
+        assert_eq!(run(&appending), 0);
+        let row = format!("1 2 8 {}\n", input);
+        assert_eq!(std::fs::read_to_string(&report).unwrap(), row.repeat(2));
+
+        // A report that doesn't end a line is kept apart from the next
+        std::fs::write(&report, "partial").unwrap();
+        assert_eq!(run(&appending), 0);
+        assert_eq!(
+            std::fs::read_to_string(&report).unwrap(),
+            format!("partial\n{}", row)
+        );
+
+        // Without --append the file is replaced
+        assert_eq!(run(&args(&["--output", &report, &input])), 0);
+        assert_eq!(std::fs::read_to_string(&report).unwrap(), row);
+    }
+
+    #[test]
+    fn split_counts_match_one_job_at_slice_edges() {
+        let args = args(&["-lwmc"]);
+        let counts = |counter: Counter| {
+            let wc = counter.finish(String::new(), &args).unwrap();
+            (wc.lines, wc.words, wc.chars, wc.bytes)
+        };
+        let cases: [(&[u8], usize); 4] = [
+            // Slice edges that cut words in two
+            (b"abcdefgh ij\n", 2),
+            (b"one two three four five", 3),
+            // Every slice edge is between a '\r' and its '\n'
+            (b"a\r\nb\r\n", 3),
+            // The slice edge at byte 5 of 10 is inside the second character
+            ("日本語\n".as_bytes(), 2),
+        ];
+        for (input, jobs) in cases {
+            let mut one_job = Counter::for_args(&args);
+            one_job.update(input).unwrap();
+            let split = count_split(input, jobs, &args).unwrap();
+            assert_eq!(counts(split), counts(one_job), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn advanced_sentences_skip_abbreviations_and_decimals() {
+        let abbreviated = temp_file("sentences-dr", b"Dr. Smith arrived. He left.\n");
+        assert_eq!(
+            text_output(&args(&["--sentences-advanced", &abbreviated])),
+            format!("2 {}\n", abbreviated)
+        );
+        let decimals = temp_file(
+            "sentences-decimal",
+            b"It costs 3.50 today. Then 2.75 tomorrow.\n",
+        );
+        assert_eq!(
+            text_output(&args(&["--sentences-advanced", &decimals])),
+            format!("2 {}\n", decimals)
+        );
+    }
+
+    #[test]
+    fn brackets_balance_and_skip_strings() {
+        let balanced = temp_file("brackets-balanced", b"f(a[{b}]) {x}\n");
+        assert_eq!(
+            text_output(&args(&["--brackets", &balanced])),
+            format!(
+                " 1  2 14 {0}\n{0}: brackets () 1/1 [] 1/1 {{}} 2/2, balanced\n",
+                balanced
+            )
+        );
+
+        let quoted = temp_file("brackets-strings", b"f(\"(\", x) + \"[}\"\n");
+        assert_eq!(
+            text_output(&args(&["--brackets", &quoted])),
+            format!(
+                " 1  4 17 {0}\n{0}: brackets () 2/1 [] 1/0 {{}} 0/1, unmatched '}}' at byte 14\n",
+                quoted
+            )
+        );
+        assert_eq!(
+            text_output(&args(&["--brackets", "--skip-strings", &quoted])),
+            format!(
+                " 1  4 17 {0}\n{0}: brackets () 1/1 [] 0/0 {{}} 0/0, balanced\n",
+                quoted
+            )
+        );
+    }
+
+    #[test]
+    fn bytes_no_eol_match_across_line_endings() {
+        let lf = temp_file("content-lf", b"first line\nsecond\n\nlast\n");
+        let crlf = temp_file("content-crlf", b"first line\r\nsecond\r\n\r\nlast\r\n");
+        let results = count(&args(&["--bytes-no-eol", &lf, &crlf]));
+        let content: Vec<u64> = results
+            .iter()
+            .map(|wc| wc.as_ref().unwrap().bytes_no_eol)
+            .collect();
+        assert_eq!(content, [20, 20]);
+    }
+
+    #[test]
+    fn whitespace_and_the_rest_make_up_the_chars() {
+        let text = "a \t b\n  c\u{a0}é\u{2003}日本\r\n";
+        let path = temp_file("whitespace-chars", text.as_bytes());
+        let results = count(&args(&["--whitespace", "-m", &path]));
+        let wc = results[0].as_ref().unwrap();
+        let other = text.chars().filter(|c| !c.is_whitespace()).count() as u64;
+        assert_eq!(wc.whitespace, 10);
+        assert_eq!(wc.whitespace + other, wc.chars);
+    }
+
+    #[test]
+    fn only_in_total_totals_the_shown_rows() {
+        let kept = temp_file("only-total.rs", b"one two\n");
+        let dropped = temp_file("only-total.md", b"three\n");
+        assert_eq!(
+            text_output(&args(&["--only=*.rs", "--only-in-total", &kept, &dropped])),
+            format!("1 2 8 {}\n1 2 8 total\n", kept)
+        );
+    }
+}